@@ -0,0 +1,28 @@
+use level_generator::generate_level::{generate_level_grid, save_level, LevelGenConfig, LevelSeed};
+use level_generator::util::get_arg;
+
+const USAGE: &str =
+    "Usage: generate_headless --output <level.json|level.ron|level.txt> [--seed <seed>] [--algorithm random-walk|wfc] [--height-map rectangles|noise|voronoi|<mask.png>] [--size 12x12x3] [--scale 3x3x2.5] [--paths 2] [--ladders 0.2] [--keep-dead-ends] [--rooms 2] [--vignettes <directory>] [--vignette-count 1] [--loops 3] [--doors 0.5] [--locks 1] [--score-band 20..40]";
+
+/// Generates a single level without starting Bevy, and writes it to the `--output` path.
+/// The format is picked from the extension of the output path.
+fn main() {
+    let output = match get_arg("--output") {
+        Some(output) => output,
+        None => {
+            eprintln!("{USAGE}");
+            std::process::exit(2);
+        }
+    };
+
+    let config = LevelGenConfig::from_args().unwrap_or_else(|err| {
+        eprintln!("{err}\n{USAGE}");
+        std::process::exit(2);
+    });
+    let mut seed = LevelSeed::new(config.seed);
+
+    let grid = generate_level_grid(&config, &mut seed.rng);
+
+    save_level(&grid, &output).unwrap_or_else(|err| panic!("Could not save {output}: {err}"));
+    println!("Saved level with seed {} to {output}", seed.seed);
+}
@@ -0,0 +1,171 @@
+use level_generator::generate_level::{
+    try_generate_level, GeneratedLevel, LevelGenConfig, LevelSeed, MAX_ATTEMPTS,
+};
+use level_generator::util::get_arg;
+use serde::Serialize;
+use std::path::Path;
+use std::time::Duration;
+
+const USAGE: &str =
+    "Usage: generate_stats --output <stats.csv|stats.json> [--count 100] [--seed <first seed>] [--algorithm random-walk|wfc] [--height-map rectangles|noise|voronoi|<mask.png>] [--size 12x12x3] [--paths 2] [--ladders 0.2] [--keep-dead-ends] [--rooms 2] [--vignettes <directory>] [--vignette-count 1] [--loops 3] [--doors 0.5] [--locks 1] [--score-band 20..40]";
+
+/// The statistics of a single level, one row of the output
+#[derive(Serialize, Default)]
+struct LevelStatistics {
+    seed: u64,
+    attempts: usize,
+    /// False when no attempt produced a valid level, the statistics after it are all 0 then
+    valid: bool,
+    score: f32,
+    walkable: usize,
+    walkable_per_storey: Vec<usize>,
+    stairs: usize,
+    branches: usize,
+    longest_path: usize,
+    loops: usize,
+    loops_added: usize,
+    expanded_diagonals: usize,
+    dead_ends_removed: usize,
+    indoor_ratio: f32,
+    height_map_ms: f64,
+    layout_ms: f64,
+    dead_ends_ms: f64,
+    loops_ms: f64,
+    validation_ms: f64,
+    metrics_ms: f64,
+}
+
+fn milliseconds(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.
+}
+
+impl LevelStatistics {
+    fn new(seed: u64, level: &GeneratedLevel) -> LevelStatistics {
+        let (metrics, stats) = (&level.metrics, &level.stats);
+
+        LevelStatistics {
+            seed,
+            attempts: stats.attempts,
+            valid: true,
+            score: level.score,
+            walkable: metrics.walkable(),
+            walkable_per_storey: metrics.walkable_per_storey.clone(),
+            stairs: metrics.stairs,
+            branches: metrics.branches,
+            longest_path: metrics.longest_path.unwrap_or_default(),
+            loops: metrics.loops,
+            loops_added: stats.loops_added,
+            expanded_diagonals: stats.expanded_diagonals,
+            dead_ends_removed: stats.dead_ends_removed,
+            indoor_ratio: metrics.indoor_ratio,
+            height_map_ms: milliseconds(stats.times.height_map),
+            layout_ms: milliseconds(stats.times.layout),
+            dead_ends_ms: milliseconds(stats.times.dead_ends),
+            loops_ms: milliseconds(stats.times.loops),
+            validation_ms: milliseconds(stats.times.validation),
+            metrics_ms: milliseconds(stats.times.metrics),
+        }
+    }
+
+    fn failed(seed: u64) -> LevelStatistics {
+        LevelStatistics {
+            seed,
+            attempts: MAX_ATTEMPTS,
+            valid: false,
+            ..LevelStatistics::default()
+        }
+    }
+}
+
+/// One line per level, the tiles per storey are separated by spaces so every row has the same columns
+fn to_csv(rows: &[LevelStatistics]) -> String {
+    let mut csv = String::from(
+        "seed,attempts,valid,score,walkable,walkable_per_storey,stairs,branches,longest_path,loops,\
+        loops_added,expanded_diagonals,dead_ends_removed,indoor_ratio,height_map_ms,layout_ms,\
+        dead_ends_ms,loops_ms,validation_ms,metrics_ms\n",
+    );
+
+    for row in rows {
+        let walkable_per_storey: Vec<String> = row
+            .walkable_per_storey
+            .iter()
+            .map(|count| count.to_string())
+            .collect();
+
+        csv += &format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3}\n",
+            row.seed,
+            row.attempts,
+            row.valid,
+            row.score,
+            row.walkable,
+            walkable_per_storey.join(" "),
+            row.stairs,
+            row.branches,
+            row.longest_path,
+            row.loops,
+            row.loops_added,
+            row.expanded_diagonals,
+            row.dead_ends_removed,
+            row.indoor_ratio,
+            row.height_map_ms,
+            row.layout_ms,
+            row.dead_ends_ms,
+            row.loops_ms,
+            row.validation_ms,
+            row.metrics_ms,
+        );
+    }
+
+    csv
+}
+
+/// Generates `--count` levels from sequential seeds starting at `--seed`, and writes their
+/// statistics to the `--output` path. Paths ending in `.json` get JSON, anything else CSV.
+fn main() {
+    let output = match get_arg("--output") {
+        Some(output) => output,
+        None => {
+            eprintln!("{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    let count: u64 = get_arg("--count")
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(100);
+
+    let mut config = LevelGenConfig::from_args().unwrap_or_else(|err| {
+        eprintln!("{err}\n{USAGE}");
+        std::process::exit(2);
+    });
+    config.measure_longest_path = true;
+    let first_seed = get_arg("--seed")
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(0);
+
+    let rows: Vec<LevelStatistics> = (first_seed..first_seed + count)
+        .map(|seed| {
+            config.seed = seed;
+            let mut seed = LevelSeed::new(seed);
+            match try_generate_level(&config, &mut seed.rng) {
+                Ok(level) => LevelStatistics::new(seed.seed, &level),
+                Err(report) => {
+                    eprintln!("Seed {} did not produce a valid level\n{report}", seed.seed);
+                    LevelStatistics::failed(seed.seed)
+                }
+            }
+        })
+        .collect();
+
+    let text = match Path::new(&output)
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some("json") => serde_json::to_string_pretty(&rows)
+            .unwrap_or_else(|err| panic!("Could not serialize the statistics: {err}")),
+        _ => to_csv(&rows),
+    };
+
+    std::fs::write(&output, text).unwrap_or_else(|err| panic!("Could not save {output}: {err}"));
+    println!("Saved statistics of {count} levels to {output}");
+}
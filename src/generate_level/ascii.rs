@@ -0,0 +1,227 @@
+use super::grid::{GridTile, LevelDimensions, LevelGrid};
+use std::fmt;
+use std::io::{Error, ErrorKind};
+
+/// Tiles as they are written in the ASCII dump.
+/// Stairs use the first letter of their direction, upper case for the top and lower case for the bottom.
+/// Ladders are an L, in the same cases.
+const GLYPHS: [(GridTile, char); 12] = [
+    (GridTile::Empty, '.'),
+    (GridTile::Floor, '#'),
+    (GridTile::StairsEastTop, 'E'),
+    (GridTile::StairsEastBottom, 'e'),
+    (GridTile::StairsNorthTop, 'N'),
+    (GridTile::StairsNorthBottom, 'n'),
+    (GridTile::StairsWestTop, 'W'),
+    (GridTile::StairsWestBottom, 'w'),
+    (GridTile::StairsSouthTop, 'S'),
+    (GridTile::StairsSouthBottom, 's'),
+    (GridTile::LadderTop, 'L'),
+    (GridTile::LadderBottom, 'l'),
+];
+
+fn tile_to_glyph(tile: GridTile) -> char {
+    GLYPHS
+        .iter()
+        .find(|(glyph_tile, _)| *glyph_tile == tile)
+        .map_or('?', |(_, glyph)| *glyph)
+}
+
+pub(super) fn glyph_to_tile(glyph: char) -> Option<GridTile> {
+    GLYPHS
+        .iter()
+        .find(|(_, tile_glyph)| *tile_glyph == glyph)
+        .map(|(tile, _)| *tile)
+}
+
+/// Prints every storey as a map of tiles, one row per y, with the height limits next to it:
+///
+/// ```text
+/// 4x2x3
+/// z=0
+/// ##e.  0011
+/// ....  0011
+/// z=1
+/// ...
+/// ```
+impl fmt::Display for LevelGrid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let size = self.size();
+        writeln!(f, "{}x{}x{}", size.0, size.1, size.2)?;
+
+        for z in 0..size.2 as isize {
+            writeln!(f, "z={z}")?;
+            for y in 0..size.1 as isize {
+                for x in 0..size.0 as isize {
+                    write!(f, "{}", tile_to_glyph(self.get(x, y, z)))?;
+                }
+                write!(f, "  ")?;
+                for x in 0..size.0 as isize {
+                    let max_height = self.get_max_height(x, y) as u32;
+                    write!(f, "{}", char::from_digit(max_height, 36).unwrap_or('?'))?;
+                }
+                writeln!(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn invalid_data(line_number: usize, message: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("line {}: {message}", line_number + 1),
+    )
+}
+
+/// Parse a grid in the format written by the `Display` implementation of `LevelGrid`.
+/// The scale, doors, locks and rooms are not part of the format, so the default scale is used
+/// and the level has no doors, locks or rooms.
+pub fn parse_grid(text: &str) -> Result<LevelGrid, Error> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+
+    let (line_number, header) = lines
+        .next()
+        .ok_or_else(|| invalid_data(0, "missing level size"))?;
+    let size: Vec<usize> = header
+        .trim()
+        .split('x')
+        .map(|part| part.parse().ok())
+        .collect::<Option<_>>()
+        .filter(|size: &Vec<usize>| size.len() == 3)
+        .ok_or_else(|| invalid_data(line_number, "expected a size like 12x12x3"))?;
+    let size = (size[0], size[1], size[2]);
+
+    let mut tiles = vec![];
+    let mut max_height: Option<Vec<Vec<u8>>> = None;
+
+    for z in 0..size.2 {
+        let (line_number, layer_header) = lines
+            .next()
+            .ok_or_else(|| invalid_data(line_number, "missing storey"))?;
+        if layer_header.trim() != format!("z={z}") {
+            return Err(invalid_data(line_number, &format!("expected z={z}")));
+        }
+
+        let mut height_rows: Vec<Vec<u8>> = vec![];
+        for y in 0..size.1 {
+            let (line_number, row) = lines
+                .next()
+                .ok_or_else(|| invalid_data(line_number, "missing row"))?;
+            let (tile_row, height_row) = row
+                .trim()
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| invalid_data(line_number, "expected tiles and heights"))?;
+            let tile_row: Vec<char> = tile_row.chars().collect();
+            let height_row: Vec<char> = height_row.trim().chars().collect();
+            if tile_row.len() != size.0 || height_row.len() != size.0 {
+                return Err(invalid_data(line_number, "row has the wrong width"));
+            }
+
+            for (x, glyph) in tile_row.into_iter().enumerate() {
+                let tile = glyph_to_tile(glyph)
+                    .ok_or_else(|| invalid_data(line_number, &format!("unknown tile {glyph}")))?;
+                tiles.push(((x, y, z), tile));
+            }
+
+            height_rows.push(
+                height_row
+                    .into_iter()
+                    .map(|glyph| {
+                        glyph
+                            .to_digit(36)
+                            .map(|height| height as u8)
+                            .ok_or_else(|| {
+                                invalid_data(line_number, &format!("invalid height {glyph}"))
+                            })
+                    })
+                    .collect::<Result<_, _>>()?,
+            );
+        }
+
+        let layer_heights: Vec<Vec<u8>> = (0..size.0)
+            .map(|x| height_rows.iter().map(|row| row[x]).collect())
+            .collect();
+
+        match &max_height {
+            Some(max_height) if *max_height != layer_heights => {
+                return Err(invalid_data(
+                    line_number,
+                    "height limits differ between storeys",
+                ))
+            }
+            Some(_) => (),
+            None => max_height = Some(layer_heights),
+        }
+    }
+
+    let mut grid = LevelGrid::new(
+        LevelDimensions {
+            size,
+            ..LevelDimensions::default()
+        },
+        max_height.unwrap_or_else(|| vec![vec![0; size.1]; size.0]),
+    );
+    for ((x, y, z), tile) in tiles {
+        grid.set(x as isize, y as isize, z as isize, tile);
+    }
+
+    return Ok(grid);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = "\
+3x2x2
+z=0
+#ew  012
+ns.  123
+z=1
+#EW  012
+NS.  123
+";
+
+    #[test]
+    fn fixture_round_trips() {
+        let grid = parse_grid(FIXTURE).unwrap();
+
+        assert_eq!(grid.get(1, 0, 0), GridTile::StairsEastBottom);
+        assert_eq!(grid.get(0, 1, 1), GridTile::StairsNorthTop);
+        assert_eq!(grid.get_max_height(2, 1), 3);
+        assert_eq!(grid.to_string(), FIXTURE);
+    }
+
+    #[test]
+    fn every_tile_and_height_round_trips() {
+        let max_height = (0..4)
+            .map(|x| (0..3).map(|y| (x * 3 + y) as u8).collect())
+            .collect();
+        let mut grid = LevelGrid::new(
+            LevelDimensions {
+                size: (4, 3, 3),
+                ..LevelDimensions::default()
+            },
+            max_height,
+        );
+        let positions: Vec<_> = grid.into_iter().collect();
+        for (&(x, y, z), &(tile, _)) in positions.iter().zip(GLYPHS.iter().cycle()) {
+            grid.set(x, y, z, tile);
+        }
+
+        assert_eq!(parse_grid(&grid.to_string()).unwrap(), grid);
+    }
+
+    #[test]
+    fn rejects_broken_dumps() {
+        assert!(parse_grid("").is_err());
+        assert!(parse_grid(&FIXTURE.replace("#ew", "#ex")).is_err());
+        assert!(parse_grid(&FIXTURE.replace("#EW  012", "#EW  013")).is_err());
+        assert!(parse_grid(&FIXTURE.replace("\nNS.  123\n", "\n")).is_err());
+    }
+}
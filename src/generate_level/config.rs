@@ -0,0 +1,254 @@
+use super::grid::{GridTile, LevelDimensions};
+use super::height_map::HeightMask;
+use super::vignettes::VignetteTemplate;
+use super::wave_function_collapse;
+use crate::util::{get_arg, has_arg};
+use rand::prelude::*;
+use std::path::PathBuf;
+
+/// The `LevelGenerator` used to build the level
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum GeneratorAlgorithm {
+    RandomWalk,
+    WaveFunctionCollapse,
+}
+
+impl GeneratorAlgorithm {
+    /// The name used for the `--algorithm` argument
+    pub fn from_name(name: &str) -> Option<GeneratorAlgorithm> {
+        match name {
+            "random-walk" => Some(GeneratorAlgorithm::RandomWalk),
+            "wfc" => Some(GeneratorAlgorithm::WaveFunctionCollapse),
+            _ => None,
+        }
+    }
+}
+
+/// Where the height limits of the columns come from
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum HeightMapSource {
+    /// Two overlapping rectangles of lower buildings, with a taller rectangle inside the first
+    Rectangles,
+    /// Blobs of buildings from thresholded value noise
+    Noise,
+    /// Districts around random points, each with a random height
+    Voronoi,
+    Mask(HeightMask),
+}
+
+impl HeightMapSource {
+    /// Reads `--height-map rectangles|noise|voronoi|<mask.png>`,
+    /// panicking if the mask can not be loaded
+    fn from_args() -> Option<HeightMapSource> {
+        match get_arg("--height-map")?.as_str() {
+            "rectangles" => Some(HeightMapSource::Rectangles),
+            "noise" => Some(HeightMapSource::Noise),
+            "voronoi" => Some(HeightMapSource::Voronoi),
+            path if path.ends_with(".png") => Some(HeightMapSource::Mask(
+                HeightMask::load(path).unwrap_or_else(|err| panic!("Could not load {path}: {err}")),
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// How much each metric counts towards the score of a level, see `LevelMetrics::score`
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct ScoreWeights {
+    pub walkable: f32,
+    pub stairs: f32,
+    pub branches: f32,
+    pub longest_path: f32,
+    pub loops: f32,
+    pub dead_ends_removed: f32,
+    pub indoor_ratio: f32,
+}
+
+/// Long paths, many stairs and many branches make a level harder, loops make it easier
+impl Default for ScoreWeights {
+    fn default() -> Self {
+        ScoreWeights {
+            walkable: 0.,
+            stairs: 1.,
+            branches: 0.5,
+            longest_path: 1.,
+            loops: -1.,
+            dead_ends_removed: 0.,
+            indoor_ratio: 0.,
+        }
+    }
+}
+
+/// The fewest storeys a level can have
+const MIN_STOREYS: usize = 3;
+
+fn parse_triple<T: std::str::FromStr>(text: &str) -> Option<(T, T, T)> {
+    let mut parts = text.split('x').map(|part| part.trim().parse().ok());
+    let triple = (parts.next()??, parts.next()??, parts.next()??);
+    match parts.next() {
+        None => Some(triple),
+        Some(_) => None,
+    }
+}
+
+/// Reads the dimensions from the `--size 12x12x3` and `--scale 3x3x2.5` arguments,
+/// using the defaults for any that are missing. Levels have to be at least one tile wide
+/// and deep and `MIN_STOREYS` high.
+fn dimensions_from_args() -> Result<LevelDimensions, String> {
+    let default = LevelDimensions::default();
+    let size = match get_arg("--size") {
+        Some(arg) => parse_triple(&arg).ok_or(format!("Invalid --size {arg}"))?,
+        None => default.size,
+    };
+    let scale = match get_arg("--scale") {
+        Some(arg) => parse_triple(&arg).ok_or(format!("Invalid --scale {arg}"))?,
+        None => default.scale,
+    };
+
+    if size.0 == 0 || size.1 == 0 || size.2 < MIN_STOREYS {
+        return Err(format!(
+            "A level needs to be at least 1x1x{MIN_STOREYS} tiles, {}x{}x{} is too small",
+            size.0, size.1, size.2
+        ));
+    }
+
+    Ok(LevelDimensions { size, scale })
+}
+
+fn parse_band(text: &str) -> Option<(f32, f32)> {
+    let (min, max) = text.split_once("..")?;
+    Some((min.trim().parse().ok()?, max.trim().parse().ok()?))
+}
+
+/// Everything that controls how a level is generated
+#[derive(Clone, PartialEq, Debug)]
+pub struct LevelGenConfig {
+    pub seed: u64,
+    pub algorithm: GeneratorAlgorithm,
+    /// At least 1x1x3 tiles
+    pub dimensions: LevelDimensions,
+    pub height_map: HeightMapSource,
+    /// Number of random walks started from the center of the level
+    pub path_count: usize,
+    /// How often the random walk climbs a ladder where a stair does not fit,
+    /// relative to its other options. With 0 it never places ladders.
+    pub ladder_weight: f32,
+    pub remove_dead_ends: bool,
+    /// How likely each tile is to be picked by the wave function collapse generator,
+    /// see `WaveFunctionCollapseGenerator::weights`
+    pub wfc_weights: Vec<(GridTile, f32)>,
+    /// How many rectangular rooms are carved into the level after the paths
+    pub room_count: usize,
+    /// Hand-made set pieces to place into the level
+    pub vignettes: Vec<VignetteTemplate>,
+    /// How many of the `vignettes` are placed, when there is room for them.
+    /// Passing `--vignettes` without `--vignette-count` places one.
+    pub vignette_count: usize,
+    /// How many loops are added after generating, so the level is not just a tree of paths
+    pub loop_count: usize,
+    /// The chance that a doorway into a building gets a door
+    pub door_chance: f32,
+    /// How many locked doors are put on the way to the goal, each with a key to find
+    pub lock_count: usize,
+    pub score_weights: ScoreWeights,
+    /// Levels are generated again until their score is between these two values
+    pub score_band: Option<(f32, f32)>,
+    /// Measure `LevelMetrics::longest_path` even without a `score_band`
+    pub measure_longest_path: bool,
+    /// Record every change to the grid, so the generation can be replayed
+    pub record_steps: bool,
+    /// Use the level saved at this path on startup instead of generating one
+    pub load_path: Option<PathBuf>,
+    /// Save the level built on startup to this path
+    pub save_path: Option<PathBuf>,
+}
+
+/// The default config uses a random seed, so every run gets a new level
+impl Default for LevelGenConfig {
+    fn default() -> Self {
+        LevelGenConfig {
+            seed: rand::thread_rng().gen(),
+            algorithm: GeneratorAlgorithm::RandomWalk,
+            dimensions: LevelDimensions::default(),
+            height_map: HeightMapSource::Rectangles,
+            path_count: 2,
+            ladder_weight: 0.,
+            remove_dead_ends: true,
+            wfc_weights: wave_function_collapse::default_weights(),
+            room_count: 0,
+            vignettes: vec![],
+            vignette_count: 0,
+            loop_count: 0,
+            door_chance: 0.,
+            lock_count: 0,
+            score_weights: ScoreWeights::default(),
+            score_band: None,
+            measure_longest_path: false,
+            record_steps: false,
+            load_path: None,
+            save_path: None,
+        }
+    }
+}
+
+impl LevelGenConfig {
+    /// Reads `--seed`, `--algorithm`, `--height-map`, `--paths`, `--ladders 0.2`,
+    /// `--keep-dead-ends`, `--rooms`, `--vignettes <directory>`, `--vignette-count`, `--loops`,
+    /// `--doors 0.5`, `--locks`, `--score-band 20..40`, `--visualize`, `--load <path>` and
+    /// `--save <path>` together with `--size 12x12x3` and `--scale 3x3x2.5`,
+    /// using the defaults for any that are missing.
+    /// Returns an error to show with the usage when the dimensions are invalid.
+    pub fn from_args() -> Result<LevelGenConfig, String> {
+        let default = LevelGenConfig::default();
+
+        Ok(LevelGenConfig {
+            seed: get_arg("--seed")
+                .and_then(|arg| arg.parse().ok())
+                .unwrap_or(default.seed),
+            algorithm: get_arg("--algorithm")
+                .and_then(|arg| GeneratorAlgorithm::from_name(&arg))
+                .unwrap_or(default.algorithm),
+            dimensions: dimensions_from_args()?,
+            height_map: HeightMapSource::from_args().unwrap_or(default.height_map),
+            path_count: get_arg("--paths")
+                .and_then(|arg| arg.parse().ok())
+                .unwrap_or(default.path_count),
+            ladder_weight: get_arg("--ladders")
+                .and_then(|arg| arg.parse().ok())
+                .unwrap_or(default.ladder_weight),
+            remove_dead_ends: !has_arg("--keep-dead-ends"),
+            wfc_weights: default.wfc_weights,
+            room_count: get_arg("--rooms")
+                .and_then(|arg| arg.parse().ok())
+                .unwrap_or(default.room_count),
+            vignettes: get_arg("--vignettes")
+                .map(|directory| {
+                    VignetteTemplate::load_directory(&directory)
+                        .unwrap_or_else(|err| panic!("Could not load {directory}: {err}"))
+                })
+                .unwrap_or(default.vignettes),
+            vignette_count: get_arg("--vignette-count")
+                .and_then(|arg| arg.parse().ok())
+                .unwrap_or(if has_arg("--vignettes") {
+                    1
+                } else {
+                    default.vignette_count
+                }),
+            loop_count: get_arg("--loops")
+                .and_then(|arg| arg.parse().ok())
+                .unwrap_or(default.loop_count),
+            door_chance: get_arg("--doors")
+                .and_then(|arg| arg.parse().ok())
+                .unwrap_or(default.door_chance),
+            lock_count: get_arg("--locks")
+                .and_then(|arg| arg.parse().ok())
+                .unwrap_or(default.lock_count),
+            score_weights: default.score_weights,
+            score_band: get_arg("--score-band").and_then(|arg| parse_band(&arg)),
+            measure_longest_path: default.measure_longest_path,
+            record_steps: has_arg("--visualize"),
+            load_path: get_arg("--load").map(PathBuf::from),
+            save_path: get_arg("--save").map(PathBuf::from),
+        })
+    }
+}
@@ -1,15 +1,111 @@
-use super::grid::{GridTile, LevelGrid, DIRECTIONS, LEVEL_SCALE, LEVEL_SIZE};
+use super::grid::{GridTile, LevelGrid};
+use super::objective::LevelObjective;
 use super::shared_models::{SharedMaterials, SharedModels};
 use crate::util::is_all_same;
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 use rand::prelude::*;
+use rand::rngs::StdRng;
 use std::f32::consts;
 
-const ROOF_HEIGHT: isize = 3;
+/// The parent of every entity spawned for the level,
+/// despawning it recursively removes the whole level
+#[derive(Component)]
+pub struct LevelRoot;
+
+/// The grid cell a piece of the level belongs to.
+/// Pieces on the edge between two cells, like walls and arches, belong to the cell with the higher coordinate.
+/// Pillars stand on the corner with the lowest coordinates of their cell.
+#[derive(Component, Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct TileCoord(pub isize, pub isize, pub isize);
+
+/// Which piece of the level an entity is
+#[derive(Component, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum LevelPiece {
+    Floor,
+    Stairs,
+    UnderStairs,
+    Ladder,
+    Door,
+    Key,
+    Roof,
+    Railing,
+    StairRailing,
+    Wall,
+    Window,
+    Pillar,
+    Arch,
+    Vase,
+    Lamp,
+    Light,
+    StartMarker,
+    GoalMarker,
+    /// The plane below the level, this one has no `TileCoord`
+    Ground,
+}
+
+/// A sensor the player can climb while they are inside it
+#[derive(Component)]
+pub struct Climbable;
+
+const DOOR_WIDTH: f32 = 1.4;
+const DOOR_HEIGHT: f32 = 2.2;
+const DOOR_THICKNESS: f32 = 0.1;
+const KEY_COLOR: Color = Color::rgb(0.9, 0.7, 0.1);
+
+/// A door in a doorway, turning around its hinge when it is opened.
+/// The door entity is the hinge, the panel is its child and only has a collider while closed.
+#[derive(Component)]
+pub struct Door {
+    pub open: bool,
+    /// The index of the lock on the door, it only opens with the matching `DoorKey`
+    pub lock: Option<usize>,
+    /// The middle of the doorway, on the floor
+    pub center: Vec3,
+    panel: Entity,
+    collider: Collider,
+}
+
+impl Door {
+    /// Swing the door open or closed, `hinge` is the transform of the door entity
+    pub fn toggle(&mut self, hinge: &mut Transform, commands: &mut Commands) {
+        self.open = !self.open;
+        if self.open {
+            hinge.rotate_y(consts::FRAC_PI_2);
+            commands.entity(self.panel).remove::<Collider>();
+        } else {
+            hinge.rotate_y(-consts::FRAC_PI_2);
+            commands.entity(self.panel).insert(self.collider.clone());
+        }
+    }
+}
+
+/// A key lying in the level, which opens the lock with index `lock`
+#[derive(Component)]
+pub struct DoorKey {
+    pub lock: usize,
+}
+
+/// Spawn an entity for a piece of the level as a child of the level root
+fn spawn_piece<'w, 's, 'a>(
+    commands: &'a mut Commands<'w, 's>,
+    root: Entity,
+    coord: (isize, isize, isize),
+    piece: LevelPiece,
+) -> bevy::ecs::system::EntityCommands<'w, 's, 'a> {
+    let entity = commands
+        .spawn()
+        .insert(TileCoord(coord.0, coord.1, coord.2))
+        .insert(piece)
+        .id();
+    commands.entity(root).add_child(entity);
+    commands.entity(entity)
+}
 
 fn spawn_railing(
     commands: &mut Commands,
+    root: Entity,
+    coord: (isize, isize, isize),
     position: Vec3,
     angle: Quat,
     wall_type: WallType,
@@ -19,11 +115,13 @@ fn spawn_railing(
         return;
     }
 
-    let model = match wall_type {
-        WallType::Short => &models.railing,
-        WallType::Tall => &models.wall,
-        WallType::StairLeft | WallType::StairRight => &models.stair_railing,
-        WallType::Window => &models.wall_window,
+    let (model, piece) = match wall_type {
+        WallType::Short => (&models.railing, LevelPiece::Railing),
+        WallType::Tall => (&models.wall, LevelPiece::Wall),
+        WallType::StairLeft | WallType::StairRight => {
+            (&models.stair_railing, LevelPiece::StairRailing)
+        }
+        WallType::Window => (&models.wall_window, LevelPiece::Window),
         _ => panic!("Invalid wall type"),
     };
 
@@ -42,7 +140,7 @@ fn spawn_railing(
         } else {
             Transform::from_xyz(position.x, position.y, position.z).with_rotation(angle)
         },
-        commands.spawn(),
+        spawn_piece(commands, root, coord, piece),
     );
 
     // commands
@@ -63,6 +161,8 @@ fn spawn_railing(
 
 fn spawn_arch(
     commands: &mut Commands,
+    root: Entity,
+    coord: (isize, isize, isize),
     position: Vec3,
     angle: Quat,
     wall_type: ArchType,
@@ -74,7 +174,7 @@ fn spawn_arch(
 
     model.arch.build(
         Transform::from_xyz(position.x, position.y, position.z).with_rotation(angle),
-        commands.spawn(),
+        spawn_piece(commands, root, coord, LevelPiece::Arch),
     );
 }
 
@@ -97,15 +197,10 @@ fn is_above_walkable(grid: &LevelGrid, xyz: (isize, isize, isize)) -> bool {
     return false;
 }
 
-/// Is this area covered by a roof?
-fn is_indoor(grid: &LevelGrid, x: isize, y: isize) -> bool {
-    grid.get_max_height(x, y) == 1
-}
-
 /// Is the area indoor but not above walkable
 /// AKA is this area both unreachable and invisible
 fn is_unreachable(grid: &LevelGrid, xyz: (isize, isize, isize)) -> bool {
-    is_indoor(grid, xyz.0, xyz.1) && !is_above_walkable(grid, xyz)
+    grid.is_indoor(xyz.0, xyz.1) && !is_above_walkable(grid, xyz)
 }
 
 #[derive(Eq, PartialEq, Copy, Clone)]
@@ -130,10 +225,16 @@ fn should_build_wall(
     p1: (isize, isize, isize),
     p2: (isize, isize, isize),
 ) -> WallType {
+    let size = grid.size();
+    // The inside of a room is open space
+    if grid.in_same_room(p1, p2) {
+        return WallType::None;
+    }
+
     // Case 1: Edge of a walkable area and the void.
     if !is_above_walkable(grid, p1)
         && !is_above_walkable(grid, p2)
-        && (p2.2..LEVEL_SIZE.2 as isize)
+        && (p2.2..size.2 as isize)
             .any(|z| is_walkable(grid, (p1.0, p1.1, z)) != is_above_walkable(grid, (p2.0, p2.1, z)))
     {
         return WallType::Tall;
@@ -142,18 +243,18 @@ fn should_build_wall(
     // Case 2: Edge of walkable area, but both sides are below walkable. This is a interior wall
     if is_above_walkable(grid, p1) ^ is_above_walkable(grid, p2)
         && !grid.can_access(p1, p2)
-        && is_above_walkable(grid, (p1.0, p1.1, LEVEL_SIZE.2 as isize - 1))
-        && is_above_walkable(grid, (p2.0, p2.1, LEVEL_SIZE.2 as isize - 1))
+        && is_above_walkable(grid, (p1.0, p1.1, size.2 as isize - 1))
+        && is_above_walkable(grid, (p2.0, p2.1, size.2 as isize - 1))
     {
         return WallType::Tall;
     }
 
     // Edge of building
-    if is_unreachable(grid, p1) != is_unreachable(grid, p2) && p1.2 <= 2 {
+    if is_unreachable(grid, p1) != is_unreachable(grid, p2) && p1.2 < size.2 as isize {
         return WallType::Tall;
     }
 
-    if is_indoor(grid, p1.0, p1.1) != is_indoor(grid, p2.0, p2.1)
+    if grid.is_indoor(p1.0, p1.1) != grid.is_indoor(p2.0, p2.1)
         && is_above_walkable(grid, p1) != is_above_walkable(grid, p2)
         && !grid.can_access(p1, p2)
     {
@@ -172,6 +273,8 @@ fn should_build_wall(
             .can_access((p1.0 - p2.0, p1.1 - p2.1)))
         && (grid.get(p1.0, p1.1, p1.2).is_bottom_stair_tile()
             || grid.get(p2.0, p2.1, p2.2).is_bottom_stair_tile()
+            || grid.get(p1.0, p1.1, p1.2).is_ladder_tile()
+            || grid.get(p2.0, p2.1, p2.2).is_ladder_tile()
             || grid.get(p1.0, p1.1, p1.2) == GridTile::Floor
             || grid.get(p2.0, p2.1, p2.2) == GridTile::Floor)
     {
@@ -199,6 +302,7 @@ fn should_build_wall(
 }
 
 fn should_build_pillar(grid: &LevelGrid, pos: (isize, isize, isize)) -> PillarType {
+    let size = grid.size();
     let corners = [
         (pos.0, pos.1, pos.2),
         (pos.0 - 1, pos.1, pos.2),
@@ -206,13 +310,22 @@ fn should_build_pillar(grid: &LevelGrid, pos: (isize, isize, isize)) -> PillarTy
         (pos.0 - 1, pos.1 - 1, pos.2),
     ];
 
-    if pos.2 < LEVEL_SIZE.2 as isize - 1
+    // No pillars in the middle of a room
+    if grid.room_at(corners[0]).is_some_and(|room| {
+        corners[1..]
+            .iter()
+            .all(|&corner| grid.room_at(corner) == Some(room))
+    }) {
+        return PillarType::None;
+    }
+
+    if pos.2 < size.2 as isize - 1
         && should_build_pillar(grid, (pos.0, pos.1, pos.2 + 1)) != PillarType::None
     {
         return PillarType::Tall;
     }
 
-    if !is_all_same(corners.iter().map(|i| is_indoor(grid, i.0, i.1))) {
+    if !is_all_same(corners.iter().map(|i| grid.is_indoor(i.0, i.1))) {
         return PillarType::Tall;
     }
 
@@ -220,7 +333,7 @@ fn should_build_pillar(grid: &LevelGrid, pos: (isize, isize, isize)) -> PillarTy
         return PillarType::None;
     }
 
-    if corners.iter().any(|i| is_indoor(grid, i.0, i.1)) {
+    if corners.iter().any(|i| grid.is_indoor(i.0, i.1)) {
         return PillarType::Tall;
     }
 
@@ -242,7 +355,8 @@ fn should_build_pillar(grid: &LevelGrid, pos: (isize, isize, isize)) -> PillarTy
     return PillarType::None;
 }
 
-fn add_floors(grid: &LevelGrid, commands: &mut Commands, models: &SharedModels) {
+fn add_floors(grid: &LevelGrid, commands: &mut Commands, root: Entity, models: &SharedModels) {
+    let scale = grid.scale();
     // let floor_mesh: Handle<Mesh> = asset_server.load("Floor.glb#Mesh0/Primitive0");
     // let stairs_mesh: Handle<Mesh> = asset_server.load("Stairs.glb#Mesh0/Primitive0");
     // let stairs_bottom_mesh: Handle<Mesh> = asset_server.load("UnderStairs.glb#Mesh0/Primitive0");
@@ -258,21 +372,18 @@ fn add_floors(grid: &LevelGrid, commands: &mut Commands, models: &SharedModels)
     // .unwrap();
 
     for (x, y, z) in grid {
-        if grid.get(x, y, z) != GridTile::Empty && !grid.get(x, y, z).is_top_stair_tile() {
-            let mesh = if grid.get(x, y, z).is_bottom_stair_tile() {
-                &models.stairs
+        // The tops of stairs and ladders are openings in the floor
+        if grid.get(x, y, z) != GridTile::Empty && !grid.get(x, y, z).goes_down() {
+            let (mesh, piece) = if grid.get(x, y, z).is_bottom_stair_tile() {
+                (&models.stairs, LevelPiece::Stairs)
             } else {
-                &models.floor
+                (&models.floor, LevelPiece::Floor)
             };
 
             mesh.build(
-                Transform::from_xyz(
-                    x as f32 * LEVEL_SCALE.0,
-                    z as f32 * LEVEL_SCALE.2,
-                    y as f32 * LEVEL_SCALE.1,
-                )
-                .with_rotation(grid.get(x, y, z).get_angle()),
-                commands.spawn(),
+                Transform::from_xyz(x as f32 * scale.0, z as f32 * scale.2, y as f32 * scale.1)
+                    .with_rotation(grid.get(x, y, z).get_angle()),
+                spawn_piece(commands, root, (x, y, z), piece),
             );
             // commands
             //     .spawn_bundle(PbrBundle {
@@ -287,9 +398,9 @@ fn add_floors(grid: &LevelGrid, commands: &mut Commands, models: &SharedModels)
             //             materials.floor.clone()
             //         },
             //         transform: Transform::from_xyz(
-            //             x as f32 * LEVEL_SCALE.0,
-            //             z as f32 * LEVEL_SCALE.2,
-            //             y as f32 * LEVEL_SCALE.1,
+            //             x as f32 * scale.0,
+            //             z as f32 * scale.2,
+            //             y as f32 * scale.1,
             //         )
             //         .with_rotation(grid.get(x, y, z).get_angle()),
             //         // .with_rotation(Quat::from_rotation_x(
@@ -305,13 +416,9 @@ fn add_floors(grid: &LevelGrid, commands: &mut Commands, models: &SharedModels)
 
             if grid.get(x, y, z).is_stair_tile() {
                 models.under_stairs.build(
-                    Transform::from_xyz(
-                        x as f32 * LEVEL_SCALE.0,
-                        z as f32 * LEVEL_SCALE.2,
-                        y as f32 * LEVEL_SCALE.1,
-                    )
-                    .with_rotation(grid.get(x, y, z).get_angle()),
-                    commands.spawn(),
+                    Transform::from_xyz(x as f32 * scale.0, z as f32 * scale.2, y as f32 * scale.1)
+                        .with_rotation(grid.get(x, y, z).get_angle()),
+                    spawn_piece(commands, root, (x, y, z), LevelPiece::UnderStairs),
                 )
             }
         }
@@ -329,6 +436,11 @@ fn should_build_arch(
     t1: (isize, isize, isize),
     t2: (isize, isize, isize),
 ) -> ArchType {
+    let size = grid.size();
+    if grid.in_same_room(t1, t2) {
+        return ArchType::None;
+    }
+
     if matches!(
         should_build_wall(grid, t1, t2),
         WallType::Tall | WallType::Window
@@ -340,16 +452,13 @@ fn should_build_arch(
         return ArchType::None;
     }
 
-    if t1.2 == LEVEL_SIZE.2 as isize - 1
-        && (is_indoor(grid, t1.0, t1.1) || is_indoor(grid, t2.0, t2.1))
-    {
+    if t1.2 == size.2 as isize - 1 && (grid.is_indoor(t1.0, t1.1) || grid.is_indoor(t2.0, t2.1)) {
         return ArchType::Normal;
     }
 
-    if (is_walkable(grid, (t1.0, t1.1, t1.2 + 1))
-        && !grid.get(t1.0, t1.1, t1.2 + 1).is_top_stair_tile())
+    if (is_walkable(grid, (t1.0, t1.1, t1.2 + 1)) && !grid.get(t1.0, t1.1, t1.2 + 1).goes_down())
         || (is_walkable(grid, (t2.0, t2.1, t2.2 + 1))
-            && !grid.get(t2.0, t2.1, t2.2 + 1).is_top_stair_tile())
+            && !grid.get(t2.0, t2.1, t2.2 + 1).goes_down())
     {
         return ArchType::Normal;
     }
@@ -357,22 +466,29 @@ fn should_build_arch(
     return ArchType::None;
 }
 
-fn add_roofs(grid: &LevelGrid, commands: &mut Commands, models: &SharedModels) {
-    for x in 0..LEVEL_SIZE.0 {
-        for y in 0..LEVEL_SIZE.1 {
-            if grid.1[x][y] == 1 {
+fn add_roofs(grid: &LevelGrid, commands: &mut Commands, root: Entity, models: &SharedModels) {
+    let size = grid.size();
+    let scale = grid.scale();
+    for x in 0..size.0 {
+        for y in 0..size.1 {
+            if grid.is_indoor(x as isize, y as isize) {
                 models.roof.build(
                     Transform::from_xyz(
-                        x as f32 * LEVEL_SCALE.0,
-                        ROOF_HEIGHT as f32 * LEVEL_SCALE.2,
-                        y as f32 * LEVEL_SCALE.1,
+                        x as f32 * scale.0,
+                        size.2 as f32 * scale.2,
+                        y as f32 * scale.1,
                     )
                     .with_rotation(Quat::from_rotation_y(if y % 2 == 0 {
                         consts::PI
                     } else {
                         0.
                     })),
-                    commands.spawn(),
+                    spawn_piece(
+                        commands,
+                        root,
+                        (x as isize, y as isize, size.2 as isize),
+                        LevelPiece::Roof,
+                    ),
                 )
                 // for (model, material) in roof_model.iter().zip(materials.iter()) {
 
@@ -380,9 +496,9 @@ fn add_roofs(grid: &LevelGrid, commands: &mut Commands, models: &SharedModels) {
                 //         mesh: model.clone(),
                 //         material: material.clone(),
                 //         transform: Transform::from_xyz(
-                //             x as f32 * LEVEL_SCALE.0,
-                //             ROOF_HEIGHT as f32 * LEVEL_SCALE.2,
-                //             y as f32 * LEVEL_SCALE.1,
+                //             x as f32 * scale.0,
+                //             size.2 as f32 * scale.2,
+                //             y as f32 * scale.1,
                 //         )
                 //         .with_rotation(Quat::from_rotation_y(
                 //             if y % 2 == 0 { consts::PI } else { 0. },
@@ -395,16 +511,20 @@ fn add_roofs(grid: &LevelGrid, commands: &mut Commands, models: &SharedModels) {
     }
 }
 
-fn add_walls(grid: &LevelGrid, commands: &mut Commands, models: &SharedModels) {
-    for x in 0..LEVEL_SIZE.0 as isize + 1 {
-        for y in 0..LEVEL_SIZE.1 as isize + 1 {
-            for z in -1..LEVEL_SIZE.2 as isize {
+fn add_walls(grid: &LevelGrid, commands: &mut Commands, root: Entity, models: &SharedModels) {
+    let size = grid.size();
+    let scale = grid.scale();
+    for x in 0..size.0 as isize + 1 {
+        for y in 0..size.1 as isize + 1 {
+            for z in -1..size.2 as isize {
                 spawn_railing(
                     commands,
+                    root,
+                    (x, y, z),
                     Vec3::new(
-                        x as f32 * LEVEL_SCALE.0 - LEVEL_SCALE.0 / 2.0,
-                        z as f32 * LEVEL_SCALE.2,
-                        y as f32 * LEVEL_SCALE.1 - LEVEL_SCALE.1 / 2.0,
+                        x as f32 * scale.0 - scale.0 / 2.0,
+                        z as f32 * scale.2,
+                        y as f32 * scale.1 - scale.1 / 2.0,
                     ),
                     Quat::from_rotation_y(-consts::FRAC_PI_2),
                     should_build_wall(grid, (x - 1, y, z), (x, y, z)),
@@ -413,10 +533,12 @@ fn add_walls(grid: &LevelGrid, commands: &mut Commands, models: &SharedModels) {
 
                 spawn_railing(
                     commands,
+                    root,
+                    (x, y, z),
                     Vec3::new(
-                        x as f32 * LEVEL_SCALE.0 - LEVEL_SCALE.0 / 2.0,
-                        z as f32 * LEVEL_SCALE.2,
-                        y as f32 * LEVEL_SCALE.1 - LEVEL_SCALE.1 / 2.0,
+                        x as f32 * scale.0 - scale.0 / 2.0,
+                        z as f32 * scale.2,
+                        y as f32 * scale.1 - scale.1 / 2.0,
                     ),
                     Quat::default(),
                     should_build_wall(grid, (x, y - 1, z), (x, y, z)),
@@ -427,16 +549,20 @@ fn add_walls(grid: &LevelGrid, commands: &mut Commands, models: &SharedModels) {
     }
 }
 
-fn build_arches(grid: &LevelGrid, commands: &mut Commands, models: &SharedModels) {
-    for x in 0..LEVEL_SIZE.0 as isize + 1 {
-        for y in 0..LEVEL_SIZE.1 as isize + 1 {
-            for z in -1..LEVEL_SIZE.2 as isize {
+fn build_arches(grid: &LevelGrid, commands: &mut Commands, root: Entity, models: &SharedModels) {
+    let size = grid.size();
+    let scale = grid.scale();
+    for x in 0..size.0 as isize + 1 {
+        for y in 0..size.1 as isize + 1 {
+            for z in -1..size.2 as isize {
                 spawn_arch(
                     commands,
+                    root,
+                    (x, y, z),
                     Vec3::new(
-                        x as f32 * LEVEL_SCALE.0 - LEVEL_SCALE.0 / 2.0,
-                        z as f32 * LEVEL_SCALE.2,
-                        y as f32 * LEVEL_SCALE.1,
+                        x as f32 * scale.0 - scale.0 / 2.0,
+                        z as f32 * scale.2,
+                        y as f32 * scale.1,
                     ),
                     Quat::default(),
                     should_build_arch(grid, (x - 1, y, z), (x, y, z)),
@@ -445,10 +571,12 @@ fn build_arches(grid: &LevelGrid, commands: &mut Commands, models: &SharedModels
 
                 spawn_arch(
                     commands,
+                    root,
+                    (x, y, z),
                     Vec3::new(
-                        x as f32 * LEVEL_SCALE.0,
-                        z as f32 * LEVEL_SCALE.2,
-                        y as f32 * LEVEL_SCALE.1 - LEVEL_SCALE.1 / 2.0,
+                        x as f32 * scale.0,
+                        z as f32 * scale.2,
+                        y as f32 * scale.1 - scale.1 / 2.0,
                     ),
                     Quat::from_rotation_y(-consts::FRAC_PI_2),
                     should_build_arch(grid, (x, y - 1, z), (x, y, z)),
@@ -459,10 +587,12 @@ fn build_arches(grid: &LevelGrid, commands: &mut Commands, models: &SharedModels
     }
 }
 
-fn add_pillars(grid: &LevelGrid, commands: &mut Commands, models: &SharedModels) {
-    for x in 0..LEVEL_SIZE.0 as isize + 1 {
-        for y in 0..LEVEL_SIZE.1 as isize + 1 {
-            for z in -1..LEVEL_SIZE.2 as isize {
+fn add_pillars(grid: &LevelGrid, commands: &mut Commands, root: Entity, models: &SharedModels) {
+    let size = grid.size();
+    let scale = grid.scale();
+    for x in 0..size.0 as isize + 1 {
+        for y in 0..size.1 as isize + 1 {
+            for z in -1..size.2 as isize {
                 let edges = should_build_pillar(grid, (x, y, z));
                 if edges != PillarType::None {
                     let model = if edges == PillarType::Tall {
@@ -473,11 +603,11 @@ fn add_pillars(grid: &LevelGrid, commands: &mut Commands, models: &SharedModels)
 
                     model.build(
                         Transform::from_xyz(
-                            x as f32 * 3. - 1.5,
-                            z as f32 * 2.5,
-                            y as f32 * 3. - 1.5,
+                            x as f32 * scale.0 - scale.0 / 2.0,
+                            z as f32 * scale.2,
+                            y as f32 * scale.1 - scale.1 / 2.0,
                         ),
-                        commands.spawn(),
+                        spawn_piece(commands, root, (x, y, z), LevelPiece::Pillar),
                     );
 
                     // commands.spawn_bundle(PbrBundle {
@@ -500,49 +630,196 @@ fn add_pillars(grid: &LevelGrid, commands: &mut Commands, models: &SharedModels)
     }
 }
 
+/// A ladder in the middle of every `LadderBottom` tile, reaching up through the opening above it.
+/// The player can climb anywhere in the column of the ladder.
+fn add_ladders(
+    grid: &LevelGrid,
+    commands: &mut Commands,
+    root: Entity,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    shared_materials: &SharedMaterials,
+) {
+    let scale = grid.scale();
+    // Sticks out above the opening, so there is something to hold on to when stepping off
+    let height = scale.2 + 1.0;
+    let rail = meshes.add(Mesh::from(shape::Box::new(0.08, height, 0.08)));
+    let rung = meshes.add(Mesh::from(shape::Box::new(0.6, 0.05, 0.05)));
+
+    for (x, y, z) in grid {
+        if grid.get(x, y, z) != GridTile::LadderBottom {
+            continue;
+        }
+
+        spawn_piece(commands, root, (x, y, z), LevelPiece::Ladder)
+            .insert_bundle(SpatialBundle::from_transform(Transform::from_translation(
+                grid.tile_position((x, y, z)),
+            )))
+            .with_children(|parent| {
+                for side in [-0.3, 0.3] {
+                    parent.spawn_bundle(PbrBundle {
+                        mesh: rail.clone(),
+                        material: shared_materials.wood.clone(),
+                        transform: Transform::from_xyz(side, height / 2., 0.),
+                        ..default()
+                    });
+                }
+
+                let mut rung_height = 0.3;
+                while rung_height < height {
+                    parent.spawn_bundle(PbrBundle {
+                        mesh: rung.clone(),
+                        material: shared_materials.wood.clone(),
+                        transform: Transform::from_xyz(0., rung_height, 0.),
+                        ..default()
+                    });
+                    rung_height += 0.3;
+                }
+
+                parent
+                    .spawn_bundle(TransformBundle::from(Transform::from_xyz(
+                        0.,
+                        height / 2.,
+                        0.,
+                    )))
+                    .insert(Collider::cuboid(0.75, height / 2., 0.75))
+                    .insert(Sensor)
+                    .insert(Climbable);
+            });
+    }
+}
+
+/// A closed door in every doorway of the grid, hinged on the side with the lower coordinates.
+/// Locked doors are the colour of their keys.
+fn add_doors(
+    grid: &LevelGrid,
+    commands: &mut Commands,
+    root: Entity,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    shared_materials: &SharedMaterials,
+) {
+    let mesh = meshes.add(Mesh::from(shape::Box::new(
+        DOOR_WIDTH,
+        DOOR_HEIGHT,
+        DOOR_THICKNESS,
+    )));
+    let locked_material = materials.add(KEY_COLOR.into());
+    let collider = Collider::cuboid(DOOR_WIDTH / 2., DOOR_HEIGHT / 2., DOOR_THICKNESS / 2.);
+
+    for &(p1, p2) in grid.doors() {
+        let center = (grid.tile_position(p1) + grid.tile_position(p2)) / 2.;
+        // Doors between tiles next to each other in x are in a wall along z
+        let rotation = if p1.0 != p2.0 {
+            Quat::from_rotation_y(-consts::FRAC_PI_2)
+        } else {
+            Quat::IDENTITY
+        };
+        let hinge = center - rotation * Vec3::X * DOOR_WIDTH / 2.;
+        let lock = grid.lock_between(p1, p2);
+
+        let panel = commands
+            .spawn_bundle(PbrBundle {
+                mesh: mesh.clone(),
+                material: match lock {
+                    Some(_) => locked_material.clone(),
+                    None => shared_materials.wood.clone(),
+                },
+                transform: Transform::from_xyz(DOOR_WIDTH / 2., DOOR_HEIGHT / 2., 0.),
+                ..default()
+            })
+            .insert(collider.clone())
+            .id();
+
+        spawn_piece(commands, root, p2, LevelPiece::Door)
+            .insert_bundle(SpatialBundle::from_transform(
+                Transform::from_translation(hinge).with_rotation(rotation),
+            ))
+            .insert(Door {
+                open: false,
+                lock,
+                center,
+                panel,
+                collider: collider.clone(),
+            })
+            .add_child(panel);
+    }
+}
+
+/// A golden cube floating above the tile of the key of every lock
+fn add_keys(
+    grid: &LevelGrid,
+    commands: &mut Commands,
+    root: Entity,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+) {
+    let mesh = meshes.add(Mesh::from(shape::Cube { size: 0.3 }));
+    let material = materials.add(StandardMaterial {
+        base_color: KEY_COLOR,
+        emissive: KEY_COLOR,
+        ..default()
+    });
+
+    for (index, lock) in grid.locks().iter().enumerate() {
+        spawn_piece(commands, root, lock.key, LevelPiece::Key)
+            .insert_bundle(PbrBundle {
+                mesh: mesh.clone(),
+                material: material.clone(),
+                transform: Transform::from_translation(
+                    grid.tile_position(lock.key) + Vec3::Y * 1.0,
+                )
+                .with_rotation(Quat::from_rotation_y(consts::FRAC_PI_4)),
+                ..default()
+            })
+            .insert(DoorKey { lock: index });
+    }
+}
+
 fn add_vases(
     grid: &LevelGrid,
     commands: &mut Commands,
+    root: Entity,
     shared_materials: &SharedMaterials,
     asset_server: &AssetServer,
+    rng: &mut StdRng,
 ) {
+    let scale = grid.scale();
     let vases: [Handle<Mesh>; 3] = [
         asset_server.load("Vase1.glb#Mesh0/Primitive0"),
         asset_server.load("Vase2.glb#Mesh0/Primitive0"),
         asset_server.load("Vase3.glb#Mesh0/Primitive0"),
     ];
 
-    let mut rng = rand::thread_rng();
     for (x, y, z) in grid {
-        if grid.get(x, y, z) == GridTile::Floor {
-            if rng.gen_range(0.0..1.0) > 0.8 {
-                commands.spawn_bundle(PbrBundle {
-                    mesh: vases[rng.gen_range(0..3)].clone(),
-                    material: shared_materials.generic.clone(),
-                    transform: Transform::from_xyz(
-                        x as f32 * 3.0 + rng.gen_range(-1.3..1.3),
-                        z as f32 * 2.5,
-                        y as f32 * 3.0 + rng.gen_range(-1.3..1.3),
-                    ),
-                    ..default()
-                });
-            }
+        if grid.get(x, y, z) == GridTile::Floor && rng.gen_range(0.0..1.0) > 0.8 {
+            spawn_piece(commands, root, (x, y, z), LevelPiece::Vase).insert_bundle(PbrBundle {
+                mesh: vases[rng.gen_range(0..3)].clone(),
+                material: shared_materials.generic.clone(),
+                transform: Transform::from_xyz(
+                    x as f32 * scale.0 + rng.gen_range(-1.3..1.3),
+                    z as f32 * scale.2,
+                    y as f32 * scale.1 + rng.gen_range(-1.3..1.3),
+                ),
+                ..default()
+            });
         }
     }
 }
 
-fn add_lights(grid: &LevelGrid, commands: &mut Commands, models: &SharedModels) {
+fn add_lights(grid: &LevelGrid, commands: &mut Commands, root: Entity, models: &SharedModels) {
+    let size = grid.size();
+    let scale = grid.scale();
     for pos in grid {
         if (pos.0 % 3 == pos.1 % 3)
-            && pos.2 == LEVEL_SIZE.2 as isize - 1
-            && is_indoor(grid, pos.0, pos.1)
+            && pos.2 == size.2 as isize - 1
+            && grid.is_indoor(pos.0, pos.1)
             && is_above_walkable(grid, pos)
         {
-            commands.spawn_bundle(PointLightBundle {
+            spawn_piece(commands, root, pos, LevelPiece::Light).insert_bundle(PointLightBundle {
                 transform: Transform::from_xyz(
-                    pos.0 as f32 * LEVEL_SCALE.0,
-                    pos.2 as f32 * LEVEL_SCALE.2,
-                    pos.1 as f32 * LEVEL_SCALE.1,
+                    pos.0 as f32 * scale.0,
+                    pos.2 as f32 * scale.2,
+                    pos.1 as f32 * scale.1,
                 ),
                 point_light: PointLight {
                     intensity: 200.0,
@@ -554,35 +831,103 @@ fn add_lights(grid: &LevelGrid, commands: &mut Commands, models: &SharedModels)
 
             models.lamp.build(
                 Transform::from_xyz(
-                    pos.0 as f32 * LEVEL_SCALE.0,
-                    pos.2 as f32 * LEVEL_SCALE.2,
-                    pos.1 as f32 * LEVEL_SCALE.1,
+                    pos.0 as f32 * scale.0,
+                    pos.2 as f32 * scale.2,
+                    pos.1 as f32 * scale.1,
                 ),
-                commands.spawn(),
+                spawn_piece(commands, root, pos, LevelPiece::Lamp),
             )
         }
     }
 }
 
+/// A green ring on the floor where the player starts, and a glowing orb above the goal
+pub(super) fn add_objective_markers(
+    grid: &LevelGrid,
+    objective: &LevelObjective,
+    commands: &mut Commands,
+    root: Entity,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+) {
+    spawn_piece(commands, root, objective.start, LevelPiece::StartMarker).insert_bundle(
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Torus {
+                radius: 0.6,
+                ring_radius: 0.08,
+                ..default()
+            })),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgb(0.2, 0.8, 0.3),
+                emissive: Color::rgb(0.1, 0.6, 0.2),
+                ..default()
+            }),
+            transform: Transform::from_translation(
+                grid.tile_position(objective.start) + Vec3::Y * 0.1,
+            ),
+            ..default()
+        },
+    );
+
+    spawn_piece(commands, root, objective.goal, LevelPiece::GoalMarker)
+        .insert_bundle(PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Icosphere {
+                radius: 0.3,
+                subdivisions: 3,
+            })),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgb(1.0, 0.8, 0.2),
+                emissive: Color::rgb(1.0, 0.7, 0.1),
+                ..default()
+            }),
+            transform: Transform::from_translation(
+                grid.tile_position(objective.goal) + Vec3::Y * 1.2,
+            ),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn_bundle(PointLightBundle {
+                point_light: PointLight {
+                    color: Color::rgb(1.0, 0.8, 0.4),
+                    intensity: 150.0,
+                    range: 5.0,
+                    ..default()
+                },
+                ..default()
+            });
+        });
+}
+
+/// Spawn every piece of the level under a new `LevelRoot` entity, which is returned
 pub(super) fn decorate_level(
     grid: &LevelGrid,
     commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
     asset_server: Res<AssetServer>,
-) {
+    rng: &mut StdRng,
+) -> Entity {
     let loading_start = std::time::Instant::now();
     let shared_materials = SharedMaterials::new(materials, &asset_server);
     let shared_models = SharedModels::new(&shared_materials, &asset_server);
 
     let start_time = std::time::Instant::now();
 
-    add_floors(grid, commands, &shared_models);
-    add_roofs(grid, commands, &shared_models);
-    add_walls(grid, commands, &shared_models);
-    add_pillars(grid, commands, &shared_models);
-    add_vases(grid, commands, &shared_materials, &asset_server);
-    build_arches(grid, commands, &shared_models);
-    add_lights(grid, commands, &shared_models);
+    let root = commands
+        .spawn_bundle(SpatialBundle::default())
+        .insert(LevelRoot)
+        .id();
+
+    add_floors(grid, commands, root, &shared_models);
+    add_ladders(grid, commands, root, meshes, &shared_materials);
+    add_doors(grid, commands, root, meshes, materials, &shared_materials);
+    add_keys(grid, commands, root, meshes, materials);
+    add_roofs(grid, commands, root, &shared_models);
+    add_walls(grid, commands, root, &shared_models);
+    add_pillars(grid, commands, root, &shared_models);
+    add_vases(grid, commands, root, &shared_materials, &asset_server, rng);
+    build_arches(grid, commands, root, &shared_models);
+    add_lights(grid, commands, root, &shared_models);
 
     let end_time = std::time::Instant::now();
     let difference = end_time - start_time;
@@ -591,4 +936,6 @@ pub(super) fn decorate_level(
         start_time - loading_start
     );
     println!("Level decoration look {:?}", difference);
+
+    root
 }
@@ -0,0 +1,43 @@
+use super::grid::{GridTile, LevelGrid};
+use rand::prelude::*;
+use rand::rngs::StdRng;
+
+/// Put doors in some of the doorways between the inside and the outside of a building.
+/// A doorway is where two floor tiles that can be walked between are on either side of the wall
+/// of a building, every doorway gets a door with a chance of `chance`.
+/// Returns the number of doors added.
+pub fn add_doors(grid: &mut LevelGrid, chance: f32, rng: &mut StdRng) -> usize {
+    // Before drawing any random numbers, so leaving doors out keeps the rest of the level
+    if chance <= 0. {
+        return 0;
+    }
+
+    let mut doorways = vec![];
+    for (x, y, z) in &*grid {
+        if grid.get(x, y, z) != GridTile::Floor {
+            continue;
+        }
+
+        // Only looking forwards, so every doorway is found once
+        for (dx, dy) in [(1, 0), (0, 1)] {
+            let other = (x + dx, y + dy, z);
+            if grid.get(other.0, other.1, other.2) == GridTile::Floor
+                && grid.can_access((x, y, z), other)
+                && grid.is_indoor(x, y) != grid.is_indoor(other.0, other.1)
+            {
+                doorways.push(((x, y, z), other));
+            }
+        }
+    }
+
+    let mut added = 0;
+    for (p1, p2) in doorways {
+        if rng.gen_bool(chance.clamp(0., 1.) as f64) {
+            grid.add_door(p1, p2);
+            added += 1;
+        }
+    }
+
+    println!("Added {added} doors");
+    added
+}
@@ -1,259 +1,79 @@
-use super::grid::{GridTile, LevelGrid, DIAGONAL_DIRECTIONS, DIRECTIONS, LEVEL_SIZE};
-use rand::prelude::*;
-
-type CursorPosition = (isize, isize, isize);
-
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
-struct Rectangle(usize, usize, usize, usize);
-
-impl Rectangle {
-    fn new_random(
-        parent: Rectangle,
-        rng: &mut ThreadRng,
-        min_size: usize,
-        max_size: usize,
-    ) -> Rectangle {
-        let size = (
-            rng.gen_range(min_size.min(parent.2)..max_size.min(parent.2)),
-            rng.gen_range(min_size.min(parent.3)..max_size.min(parent.3)),
-        );
-
-        let position = (
-            rng.gen_range(parent.0..parent.0 + parent.2 - size.0),
-            rng.gen_range(parent.1..parent.1 + parent.3 - size.1),
-        );
-
-        return Rectangle(position.0, position.1, size.0, size.1);
-    }
-
-    fn is_inside(&self, position: (usize, usize)) -> bool {
-        self.0 <= position.0
-            && position.1 < self.0 + self.2
-            && self.1 <= position.1
-            && position.1 < self.1 + self.3
-    }
+use super::config::{GeneratorAlgorithm, LevelGenConfig};
+use super::doors::add_doors;
+use super::grid::{GenerationStep, GridTile, LevelGrid, DIRECTIONS};
+use super::locks::add_locks;
+use super::loops::add_loops;
+use super::metrics::LevelMetrics;
+use super::random_walk::RandomWalkGenerator;
+use super::rooms::add_rooms;
+use super::validate::ValidationReport;
+use super::vignettes::add_vignettes;
+use super::wave_function_collapse::WaveFunctionCollapseGenerator;
+use rand::rngs::StdRng;
+use std::time::{Duration, Instant};
+
+/// How many times generation is retried before giving up on a config
+pub const MAX_ATTEMPTS: usize = 50;
+
+/// How long each phase of generating a level took
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct PhaseTimes {
+    pub height_map: Duration,
+    /// Placing the tiles, like the random walks or collapsing the wave function
+    pub layout: Duration,
+    pub dead_ends: Duration,
+    pub loops: Duration,
+    pub validation: Duration,
+    pub metrics: Duration,
 }
 
-fn generate_height_limits(rng: &mut ThreadRng) -> [[u8; LEVEL_SIZE.1]; LEVEL_SIZE.0] {
-    let outer_rect_1 = Rectangle::new_random(
-        Rectangle(0, 0, LEVEL_SIZE.0, LEVEL_SIZE.1),
-        rng,
-        LEVEL_SIZE.0 / 2,
-        LEVEL_SIZE.0 * 3 / 4,
-    );
-
-    let outer_rect_2 = Rectangle::new_random(
-        Rectangle(0, 0, LEVEL_SIZE.0, LEVEL_SIZE.1),
-        rng,
-        LEVEL_SIZE.0 / 2,
-        LEVEL_SIZE.0 * 3 / 4,
-    );
-
-    let inner_rect = Rectangle::new_random(outer_rect_1, rng, LEVEL_SIZE.0 / 5, LEVEL_SIZE.0 / 2);
-
-    let mut out = [[0; LEVEL_SIZE.1]; LEVEL_SIZE.0];
-
-    for x in 0..LEVEL_SIZE.0 {
-        for y in 0..LEVEL_SIZE.1 {
-            out[x][y] = if inner_rect.is_inside((x, y)) {
-                2
-            } else if outer_rect_1.is_inside((x, y)) || outer_rect_2.is_inside((x, y)) {
-                1
-            } else {
-                0
-            }
-        }
-    }
-
-    return out;
+/// Counts kept while generating a level, that can not be seen in the finished grid
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct GenerationStats {
+    /// Stairs the random walk did not place, because they would be diagonal from the same stair
+    pub expanded_diagonals: usize,
+    pub dead_ends_removed: usize,
+    pub rooms_added: usize,
+    pub vignettes_added: usize,
+    pub loops_added: usize,
+    pub doors_added: usize,
+    pub locks_added: usize,
+    /// How many levels were generated before one was accepted
+    pub attempts: usize,
+    /// The times of the accepted attempt
+    pub times: PhaseTimes,
 }
 
-fn generate_options(
-    grid: &LevelGrid,
-    cursor_position: CursorPosition,
-) -> Vec<(GridTile, (isize, isize), Option<(isize, isize)>)> {
-    let mut options = Vec::new();
-    let mut expanded_diagonals = 0;
-
-    for cardinal_direction in [
-        (-1, 0, GridTile::StairsWestTop, GridTile::StairsEastBottom),
-        (0, -1, GridTile::StairsNorthTop, GridTile::StairsSouthBottom),
-        (1, 0, GridTile::StairsEastTop, GridTile::StairsWestBottom),
-        (0, 1, GridTile::StairsSouthTop, GridTile::StairsNorthBottom),
-    ] {
-        let offset_tile_position = (
-            cardinal_direction.0 + cursor_position.0,
-            cardinal_direction.1 + cursor_position.1,
-        );
-
-        if grid.get(
-            offset_tile_position.0,
-            offset_tile_position.1,
-            cursor_position.2,
-        ) == GridTile::Empty
-            && grid.is_valid_height(
-                offset_tile_position.0,
-                offset_tile_position.1,
-                cursor_position.2,
-            )
-        {
-            options.push((GridTile::Floor, offset_tile_position, None));
-
-            let expanded_grid_tile = (
-                (offset_tile_position.0 + cardinal_direction.0),
-                (offset_tile_position.1 + cardinal_direction.1),
-            );
-
-            if DIRECTIONS.iter().any(|dir| {
-                grid.get(
-                    offset_tile_position.0 + dir.0,
-                    offset_tile_position.1 + dir.1,
-                    cursor_position.2,
-                )
-                .is_stair_tile()
-            }) {
-                continue;
-            }
-
-            if grid.get(
-                offset_tile_position.0,
-                offset_tile_position.1,
-                cursor_position.2 - 1,
-            ) == GridTile::Empty
-                && grid.get(
-                    expanded_grid_tile.0,
-                    expanded_grid_tile.1,
-                    cursor_position.2 - 1,
-                ) == GridTile::Empty
-                && grid.is_valid_height(
-                    expanded_grid_tile.0,
-                    expanded_grid_tile.1,
-                    cursor_position.2 - 1,
-                )
-            {
-                // Prevent the same stair spawning diagonal from itself, which looks ugly
-                if !DIAGONAL_DIRECTIONS.iter().any(|(a, b)| {
-                    grid.get(
-                        offset_tile_position.0 + a,
-                        offset_tile_position.1 + b,
-                        cursor_position.2,
-                    ) == cardinal_direction.2
-                }) {
-                    options.push((
-                        cardinal_direction.2,
-                        offset_tile_position,
-                        Some(expanded_grid_tile),
-                    ))
-                } else {
-                    expanded_diagonals += 1;
-                }
-            }
-
-            if grid.get(
-                offset_tile_position.0,
-                offset_tile_position.1,
-                cursor_position.2 + 1,
-            ) == GridTile::Empty
-                && grid.get(
-                    expanded_grid_tile.0,
-                    expanded_grid_tile.1,
-                    cursor_position.2 + 1,
-                ) == GridTile::Empty
-                && grid.is_valid_height(
-                    expanded_grid_tile.0,
-                    expanded_grid_tile.1,
-                    cursor_position.2 + 1,
-                )
-            {
-                // Prevent the same stair spawning diagonal from itself, which looks ugly
-                if !DIAGONAL_DIRECTIONS.iter().any(|(a, b)| {
-                    grid.get(
-                        offset_tile_position.0 + a,
-                        offset_tile_position.1 + b,
-                        cursor_position.2,
-                    ) == cardinal_direction.3
-                }) {
-                    options.push((
-                        cardinal_direction.3,
-                        offset_tile_position,
-                        Some(expanded_grid_tile),
-                    ))
-                } else {
-                    expanded_diagonals += 1;
-                }
-            }
-        }
-    }
-
-    if expanded_diagonals > 0 {
-        println!("Removed expanded diagonals: {expanded_diagonals} at {cursor_position:?}");
-    }
-
-    return options;
+/// An algorithm that fills a `LevelGrid`.
+/// Every call should only use `rng` for randomness, so the same seed gives the same level.
+pub trait LevelGenerator {
+    fn generate(
+        &self,
+        config: &LevelGenConfig,
+        rng: &mut StdRng,
+        stats: &mut GenerationStats,
+    ) -> LevelGrid;
 }
 
-fn generate_single_path(grid: &mut LevelGrid, mut cursor_position: CursorPosition) {
-    let mut rng = rand::thread_rng();
-
-    loop {
-        let options = generate_options(&grid, cursor_position);
-
-        println!("> {:?}", cursor_position);
-        match options.choose(&mut rng) {
-            Some((new_tile_type, new_tile_position, new_new_tile_position)) => {
-                // get_grid_at(&grid, new_tile_position.0, new_tile_position.1, cursor_position.2);
-                assert_eq!(
-                    grid.get(new_tile_position.0, new_tile_position.1, cursor_position.2),
-                    GridTile::Empty
-                );
-                grid.set(
-                    new_tile_position.0,
-                    new_tile_position.1,
-                    cursor_position.2,
-                    *new_tile_type,
-                );
-                cursor_position.0 = new_tile_position.0;
-                cursor_position.1 = new_tile_position.1;
-                if new_tile_type.is_stair_tile() {
-                    if new_tile_type.is_bottom_stair_tile() {
-                        cursor_position.2 += 1;
-                    } else if new_tile_type.is_top_stair_tile() {
-                        cursor_position.2 -= 1;
-                    }
-                    println!("# {:?}", cursor_position);
-                    grid.set(
-                        new_tile_position.0,
-                        new_tile_position.1,
-                        cursor_position.2,
-                        new_tile_type.get_opposite_stair_tile().unwrap(),
-                    );
-
-                    let new_new_tile_position = new_new_tile_position.unwrap();
-                    cursor_position.0 = new_new_tile_position.0;
-                    cursor_position.1 = new_new_tile_position.1;
-
-                    grid.set(
-                        cursor_position.0,
-                        cursor_position.1,
-                        cursor_position.2 as isize,
-                        GridTile::Floor,
-                    );
-                }
-            }
-            None => break,
-        }
-    }
+/// A generated level together with what is known about how it was made
+pub struct GeneratedLevel {
+    pub grid: Box<LevelGrid>,
+    /// Every change made to the grid, only recorded when `record_steps` is set in the config
+    pub steps: Vec<GenerationStep>,
+    pub stats: GenerationStats,
+    pub metrics: LevelMetrics,
+    pub score: f32,
 }
 
 fn is_dead_end(grid: &LevelGrid, x: isize, y: isize, z: isize) -> bool {
     let tile = grid.get(x, y, z);
 
     let mut nr = 0;
-    if tile.is_bottom_stair_tile() && grid.get(x, y, z + 1).is_top_stair_tile() {
+    if tile.goes_up() && grid.can_access((x, y, z), (x, y, z + 1)) {
         nr += 1;
     }
 
-    if tile.is_top_stair_tile() && grid.get(x, y, z - 1).is_bottom_stair_tile() {
+    if tile.goes_down() && grid.can_access((x, y, z), (x, y, z - 1)) {
         nr += 1;
     }
 
@@ -270,27 +90,40 @@ fn is_dead_end(grid: &LevelGrid, x: isize, y: isize, z: isize) -> bool {
     return nr == 1;
 }
 
-fn remove_dead_ends(grid: &mut LevelGrid) {
+/// Remove tiles with a single neighbour until there are none left, keeping track of how many
+/// were removed and how long it took
+pub(super) fn remove_dead_ends(grid: &mut LevelGrid, stats: &mut GenerationStats) {
+    let start_time = Instant::now();
     let mut nrof_dead_ends_removed = 0;
     let mut dead_ends = vec![];
 
-    for x in 0..LEVEL_SIZE.0 as isize {
-        for y in 0..LEVEL_SIZE.1 as isize {
-            for z in 0..LEVEL_SIZE.2 as isize {
-                if is_dead_end(grid, x, y, z) {
-                    dead_ends.push((x, y, z));
-                }
-            }
+    for (x, y, z) in &*grid {
+        if is_dead_end(grid, x, y, z) {
+            dead_ends.push((x, y, z));
         }
     }
 
-    while dead_ends.len() > 0 {
+    while !dead_ends.is_empty() {
         let mut new_dead_ends = vec![];
 
         for (x, y, z) in dead_ends {
+            let tile = grid.get(x, y, z);
             grid.set(x, y, z, GridTile::Empty);
             nrof_dead_ends_removed += 1;
 
+            // Unlike a stair, the other half of a ladder can still be walked onto from other
+            // sides, so it stays as a floor
+            let other_z = if tile == GridTile::LadderBottom {
+                z + 1
+            } else {
+                z - 1
+            };
+            if tile.is_ladder_tile()
+                && Some(grid.get(x, y, other_z)) == tile.get_opposite_vertical_tile()
+            {
+                grid.set(x, y, other_z, GridTile::Floor);
+            }
+
             for (x, y, z) in [
                 (x + 1, y, z),
                 (x - 1, y, z),
@@ -309,23 +142,136 @@ fn remove_dead_ends(grid: &mut LevelGrid) {
     }
 
     println!("Removed {nrof_dead_ends_removed} dead ends");
+
+    stats.dead_ends_removed = nrof_dead_ends_removed;
+    stats.times.dead_ends = start_time.elapsed();
 }
 
-pub(super) fn generate_level_grid() -> Box<LevelGrid> {
-    let mut grid = LevelGrid::new(generate_height_limits(&mut rand::thread_rng()));
+/// Generate a level with the algorithm picked in the config,
+/// panicking when no attempt produces a valid level
+pub fn generate_level(config: &LevelGenConfig, rng: &mut StdRng) -> GeneratedLevel {
+    try_generate_level(config, rng).unwrap_or_else(|report| {
+        panic!("Could not generate a valid level in {MAX_ATTEMPTS} attempts\n{report}")
+    })
+}
 
-    let cursor_position: CursorPosition = (6, 6, grid.1[6][6] as isize);
-    grid.set(
-        cursor_position.0,
-        cursor_position.1,
-        cursor_position.2,
-        GridTile::Floor,
-    );
+/// Generate a level with the algorithm picked in the config,
+/// or return the report of the last attempt when none of them produced a valid level
+pub fn try_generate_level(
+    config: &LevelGenConfig,
+    rng: &mut StdRng,
+) -> Result<GeneratedLevel, ValidationReport> {
+    match config.algorithm {
+        GeneratorAlgorithm::RandomWalk => {
+            try_generate_level_with(&RandomWalkGenerator, config, rng)
+        }
+        GeneratorAlgorithm::WaveFunctionCollapse => {
+            let generator = WaveFunctionCollapseGenerator {
+                weights: config.wfc_weights.clone(),
+            };
+            try_generate_level_with(&generator, config, rng)
+        }
+    }
+}
+
+/// Generate a level with the algorithm picked in the config, without the metrics
+pub fn generate_level_grid(config: &LevelGenConfig, rng: &mut StdRng) -> Box<LevelGrid> {
+    generate_level(config, rng).grid
+}
+
+/// How far a score is outside the band, 0 when it is inside
+fn distance_to_band(score: f32, band: Option<(f32, f32)>) -> f32 {
+    match band {
+        Some((min, _)) if score < min => min - score,
+        Some((_, max)) if score > max => score - max,
+        _ => 0.,
+    }
+}
+
+/// Generate a level with `generator` and add rooms, vignettes, loops, doors and locks to it.
+/// Levels that break one of the invariants checked by `LevelGrid::validate`, or that score outside
+/// of the score band of the config, are thrown away and generated again.
+/// When no level scores inside the band the closest valid one is used,
+/// and when no attempt produces a valid level at all this panics with the last report.
+pub fn generate_level_with(
+    generator: &dyn LevelGenerator,
+    config: &LevelGenConfig,
+    rng: &mut StdRng,
+) -> GeneratedLevel {
+    try_generate_level_with(generator, config, rng).unwrap_or_else(|report| {
+        panic!("Could not generate a valid level in {MAX_ATTEMPTS} attempts\n{report}")
+    })
+}
 
-    generate_single_path(&mut grid, cursor_position);
-    generate_single_path(&mut grid, cursor_position);
+/// Like `generate_level_with`, but returns the report of the last attempt
+/// when no attempt produces a valid level
+pub fn try_generate_level_with(
+    generator: &dyn LevelGenerator,
+    config: &LevelGenConfig,
+    rng: &mut StdRng,
+) -> Result<GeneratedLevel, ValidationReport> {
+    let mut report = None;
+    let mut closest: Option<GeneratedLevel> = None;
+    for attempt in 1..=MAX_ATTEMPTS {
+        let mut stats = GenerationStats {
+            attempts: attempt,
+            ..GenerationStats::default()
+        };
+        let mut grid = generator.generate(config, rng, &mut stats);
+        stats.rooms_added = add_rooms(&mut grid, config.room_count, rng);
+        stats.vignettes_added =
+            add_vignettes(&mut grid, &config.vignettes, config.vignette_count, rng);
+
+        let start_time = Instant::now();
+        stats.loops_added = add_loops(&mut grid, config.loop_count);
+        stats.times.loops = start_time.elapsed();
+        stats.doors_added = add_doors(&mut grid, config.door_chance, rng);
+        stats.locks_added = add_locks(&mut grid, config.lock_count, rng);
+
+        let start_time = Instant::now();
+        let attempt_report = grid.validate();
+        stats.times.validation = start_time.elapsed();
+        if !attempt_report.is_valid() {
+            println!("Attempt {attempt} produced an invalid level\n{attempt_report}");
+            report = Some(attempt_report);
+            continue;
+        }
 
-    remove_dead_ends(&mut grid);
+        let start_time = Instant::now();
+        let metrics = LevelMetrics::measure(
+            &grid,
+            &stats,
+            config.score_band.is_some() || config.measure_longest_path,
+        );
+        stats.times.metrics = start_time.elapsed();
+        let score = metrics.score(&config.score_weights);
+        let level = GeneratedLevel {
+            steps: grid.take_steps(),
+            grid: Box::new(grid),
+            stats,
+            metrics,
+            score,
+        };
+
+        if distance_to_band(score, config.score_band) == 0. {
+            closest = Some(level);
+            break;
+        }
 
-    return Box::new(grid);
+        println!(
+            "Attempt {attempt} scored {score}, outside of {:?}",
+            config.score_band
+        );
+        if closest.as_ref().is_none_or(|closest| {
+            distance_to_band(score, config.score_band)
+                < distance_to_band(closest.score, config.score_band)
+        }) {
+            closest = Some(level);
+        }
+    }
+
+    match closest {
+        Some(level) => Ok(level),
+        None => Err(report.unwrap()),
+    }
 }
@@ -1,16 +1,91 @@
-use bevy::prelude::*;
+use glam::{Quat, Vec3};
+use serde::{Deserialize, Serialize};
 use std::f32::consts;
-pub(super) const LEVEL_SIZE: (usize, usize, usize) = (12, 12, 3);
-pub(super) const LEVEL_SCALE: (f32, f32, f32) = (3., 3., 2.5);
 
-pub struct LevelGrid(
-    [[[GridTile; LEVEL_SIZE.2]; LEVEL_SIZE.1]; LEVEL_SIZE.0],
-    pub [[u8; LEVEL_SIZE.1]; LEVEL_SIZE.0],
-);
+/// The number of tiles in each direction (width, depth, storeys) and the size of
+/// a single tile in world units.
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct LevelDimensions {
+    pub size: (usize, usize, usize),
+    pub scale: (f32, f32, f32),
+}
+
+impl Default for LevelDimensions {
+    fn default() -> Self {
+        LevelDimensions {
+            size: (12, 12, 3),
+            scale: (3., 3., 2.5),
+        }
+    }
+}
+
+/// The maximum height of each column, indexed as `[x][y]`
+pub type HeightMap = Vec<Vec<u8>>;
+
+/// A single change made to the grid while it was generated, see `LevelGrid::start_recording`
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum GenerationStep {
+    /// A tile was placed, or removed when it is `Empty`
+    Set {
+        position: (isize, isize, isize),
+        tile: GridTile,
+    },
+    /// A random walk had nowhere left to go
+    WalkStalled { position: (isize, isize, isize) },
+    /// A stair was not placed, because it would be diagonal from the same stair
+    StairRejected { position: (isize, isize, isize) },
+}
+
+/// The two tiles on either side of a door, next to each other on the same storey.
+/// The tile with the lower coordinates comes first.
+pub type DoorEdge = ((isize, isize, isize), (isize, isize, isize));
+
+/// A door that only opens after picking up the key lying at `key`
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Lock {
+    pub door: DoorEdge,
+    pub key: (isize, isize, isize),
+}
+
+/// A rectangle of floor tiles on a single storey that is decorated as one open space.
+/// `min` and `max` are the x and y of the corners, both inside the room.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Room {
+    pub min: (isize, isize),
+    pub max: (isize, isize),
+    pub z: isize,
+}
+
+impl Room {
+    pub fn contains(&self, (x, y, z): (isize, isize, isize)) -> bool {
+        z == self.z
+            && (self.min.0..=self.max.0).contains(&x)
+            && (self.min.1..=self.max.1).contains(&y)
+    }
+
+    /// Every tile of the room
+    pub fn tiles(&self) -> impl Iterator<Item = (isize, isize, isize)> + '_ {
+        (self.min.0..=self.max.0)
+            .flat_map(move |x| (self.min.1..=self.max.1).map(move |y| (x, y, self.z)))
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct LevelGrid {
+    /// Indexed as `[x][y][z]`
+    tiles: Vec<Vec<Vec<GridTile>>>,
+    pub max_height: HeightMap,
+    dimensions: LevelDimensions,
+    /// Every change made with `set`, while the grid is being recorded
+    steps: Option<Vec<GenerationStep>>,
+    doors: Vec<DoorEdge>,
+    locks: Vec<Lock>,
+    rooms: Vec<Room>,
+}
 pub const DIRECTIONS: [(isize, isize); 4] = [(1, 0), (-1, 0), (0, -1), (0, 1)];
 pub const DIAGONAL_DIRECTIONS: [(isize, isize); 4] = [(1, 1), (-1, 1), (1, -1), (-1, -1)];
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
 pub enum GridTile {
     Empty,
     Floor,
@@ -22,10 +97,42 @@ pub enum GridTile {
     StairsWestBottom,
     StairsSouthTop,
     StairsSouthBottom,
+    /// A ladder going up to the `LadderTop` right above it
+    LadderBottom,
+    /// The opening in the floor a ladder leads up to
+    LadderTop,
     OutOfBounds,
 }
 
 impl GridTile {
+    /// Floors, stairs and ladders, the tiles the player can stand on
+    pub fn is_walkable(self) -> bool {
+        self == GridTile::Floor || self.is_stair_tile() || self.is_ladder_tile()
+    }
+
+    pub fn is_ladder_tile(self) -> bool {
+        self == GridTile::LadderBottom || self == GridTile::LadderTop
+    }
+
+    /// The bottom of a stair or ladder, which leads to the tile above it
+    pub fn goes_up(self) -> bool {
+        self.is_bottom_stair_tile() || self == GridTile::LadderBottom
+    }
+
+    /// The top of a stair or ladder, which leads to the tile below it
+    pub fn goes_down(self) -> bool {
+        self.is_top_stair_tile() || self == GridTile::LadderTop
+    }
+
+    /// The other half of a stair or ladder
+    pub fn get_opposite_vertical_tile(self) -> Option<GridTile> {
+        match self {
+            GridTile::LadderBottom => Some(GridTile::LadderTop),
+            GridTile::LadderTop => Some(GridTile::LadderBottom),
+            _ => self.get_opposite_stair_tile(),
+        }
+    }
+
     pub fn is_stair_tile(self) -> bool {
         match self {
             GridTile::StairsEastTop
@@ -101,8 +208,11 @@ impl GridTile {
         // }
         match (self, direction) {
             (GridTile::Empty, _) => false,
-            (GridTile::Floor, (0, 1) | (0, -1) | (1, 0) | (-1, 0)) => true,
-            (GridTile::Floor, _) => false,
+            (
+                GridTile::Floor | GridTile::LadderBottom | GridTile::LadderTop,
+                (0, 1) | (0, -1) | (1, 0) | (-1, 0),
+            ) => true,
+            (GridTile::Floor | GridTile::LadderBottom | GridTile::LadderTop, _) => false,
             (GridTile::StairsNorthBottom | GridTile::StairsSouthTop, (0, -1)) => true,
             (GridTile::StairsSouthBottom | GridTile::StairsNorthTop, (0, 1)) => true,
             (GridTile::StairsEastBottom | GridTile::StairsWestTop, (1, 0)) => true,
@@ -113,67 +223,201 @@ impl GridTile {
 }
 
 impl LevelGrid {
-    pub fn new(max_height: [[u8; LEVEL_SIZE.1]; LEVEL_SIZE.0]) -> LevelGrid {
-        LevelGrid(
-            [[[GridTile::Empty; LEVEL_SIZE.2]; LEVEL_SIZE.1]; LEVEL_SIZE.0],
+    pub fn new(dimensions: LevelDimensions, max_height: HeightMap) -> LevelGrid {
+        let size = dimensions.size;
+        assert_eq!(max_height.len(), size.0, "Height map has the wrong width");
+        assert!(
+            max_height.iter().all(|column| column.len() == size.1),
+            "Height map has the wrong depth"
+        );
+
+        LevelGrid {
+            tiles: vec![vec![vec![GridTile::Empty; size.2]; size.1]; size.0],
             max_height,
-        )
+            dimensions,
+            steps: None,
+            doors: vec![],
+            locks: vec![],
+            rooms: vec![],
+        }
+    }
+
+    /// Keep every change made with `set` from now on, to replay how the level was made
+    pub fn start_recording(&mut self) {
+        self.steps = Some(vec![]);
+    }
+
+    /// Add a step to the recording, if the grid is being recorded
+    pub fn record(&mut self, step: GenerationStep) {
+        if let Some(steps) = &mut self.steps {
+            steps.push(step);
+        }
+    }
+
+    /// Stop recording and return the recorded steps
+    pub fn take_steps(&mut self) -> Vec<GenerationStep> {
+        self.steps.take().unwrap_or_default()
+    }
+
+    pub fn dimensions(&self) -> LevelDimensions {
+        self.dimensions
+    }
+
+    pub fn size(&self) -> (usize, usize, usize) {
+        self.dimensions.size
+    }
+
+    pub fn scale(&self) -> (f32, f32, f32) {
+        self.dimensions.scale
     }
 
     pub fn get(&self, x: isize, y: isize, z: isize) -> GridTile {
         if x < 0
-            || x >= LEVEL_SIZE.0 as isize
+            || x >= self.size().0 as isize
             || y < 0
-            || y >= LEVEL_SIZE.1 as isize
+            || y >= self.size().1 as isize
             || z < 0
-            || z >= LEVEL_SIZE.2 as isize
+            || z >= self.size().2 as isize
         {
             return GridTile::OutOfBounds;
         }
-        return self.0[x as usize][y as usize][z as usize];
+        return self.tiles[x as usize][y as usize][z as usize];
     }
 
     pub fn set(&mut self, x: isize, y: isize, z: isize, val: GridTile) {
-        self.0[x as usize][y as usize][z as usize] = val;
+        self.tiles[x as usize][y as usize][z as usize] = val;
+        self.record(GenerationStep::Set {
+            position: (x, y, z),
+            tile: val,
+        });
+    }
+
+    /// Put a door between two tiles next to each other on the same storey
+    pub fn add_door(&mut self, p1: (isize, isize, isize), p2: (isize, isize, isize)) {
+        let edge = if p1 <= p2 { (p1, p2) } else { (p2, p1) };
+        if !self.doors.contains(&edge) {
+            self.doors.push(edge);
+        }
+    }
+
+    pub fn has_door(&self, p1: (isize, isize, isize), p2: (isize, isize, isize)) -> bool {
+        self.doors.contains(&(p1, p2)) || self.doors.contains(&(p2, p1))
+    }
+
+    pub fn doors(&self) -> &[DoorEdge] {
+        &self.doors
+    }
+
+    /// Put a locked door between two tiles, with its key at `key`. Returns the index of the lock.
+    pub fn add_lock(
+        &mut self,
+        p1: (isize, isize, isize),
+        p2: (isize, isize, isize),
+        key: (isize, isize, isize),
+    ) -> usize {
+        self.add_door(p1, p2);
+        self.locks.push(Lock {
+            door: if p1 <= p2 { (p1, p2) } else { (p2, p1) },
+            key,
+        });
+        self.locks.len() - 1
+    }
+
+    pub fn locks(&self) -> &[Lock] {
+        &self.locks
+    }
+
+    /// The index of the lock on the door between two tiles, if there is one
+    pub fn lock_between(
+        &self,
+        p1: (isize, isize, isize),
+        p2: (isize, isize, isize),
+    ) -> Option<usize> {
+        self.locks
+            .iter()
+            .position(|lock| lock.door == (p1, p2) || lock.door == (p2, p1))
+    }
+
+    pub fn add_room(&mut self, room: Room) {
+        self.rooms.push(room);
+    }
+
+    pub fn rooms(&self) -> &[Room] {
+        &self.rooms
+    }
+
+    /// The index of the room a tile is part of
+    pub fn room_at(&self, position: (isize, isize, isize)) -> Option<usize> {
+        self.rooms.iter().position(|room| room.contains(position))
+    }
+
+    /// Whether both tiles are inside the same room, so there is open space between them
+    pub fn in_same_room(&self, p1: (isize, isize, isize), p2: (isize, isize, isize)) -> bool {
+        self.room_at(p1)
+            .is_some_and(|room| self.room_at(p2) == Some(room))
     }
 
     pub fn is_valid_height(&self, x: isize, y: isize, z: isize) -> bool {
         if x < 0
-            || x >= LEVEL_SIZE.0 as isize
+            || x >= self.size().0 as isize
             || y < 0
-            || y >= LEVEL_SIZE.1 as isize
+            || y >= self.size().1 as isize
             || z < 0
-            || z >= LEVEL_SIZE.2 as isize
+            || z >= self.size().2 as isize
         {
             return false;
         }
 
-        let max_height = self.1[x as usize][y as usize];
+        let max_height = self.max_height[x as usize][y as usize];
         return z <= max_height as isize && z + 1 >= max_height as isize;
     }
 
     pub fn get_max_height(&self, x: isize, y: isize) -> u8 {
-        if x < 0 || x >= LEVEL_SIZE.0 as isize || y < 0 || y >= LEVEL_SIZE.1 as isize {
+        if x < 0 || x >= self.size().0 as isize || y < 0 || y >= self.size().1 as isize {
             return 0;
         }
 
-        return self.1[x as usize][y as usize];
+        return self.max_height[x as usize][y as usize];
+    }
+
+    /// Columns that are one storey lower than the tallest buildings are inside a building
+    pub fn is_indoor(&self, x: isize, y: isize) -> bool {
+        self.get_max_height(x, y) as usize + 2 == self.size().2
+    }
+
+    /// The world position of the center of the floor of a tile
+    pub fn tile_position(&self, xyz: (isize, isize, isize)) -> Vec3 {
+        let scale = self.scale();
+        Vec3::new(
+            xyz.0 as f32 * scale.0,
+            xyz.2 as f32 * scale.2,
+            xyz.1 as f32 * scale.1,
+        )
+    }
+
+    /// The floor tile closest to the center of the level, a safe place to put the player
+    pub fn find_spawn_tile(&self) -> Option<(isize, isize, isize)> {
+        let center = (self.size().0 as isize / 2, self.size().1 as isize / 2);
+
+        self.into_iter()
+            .filter(|&(x, y, z)| self.get(x, y, z) == GridTile::Floor)
+            .min_by_key(|&(x, y, _)| (x - center.0).abs() + (y - center.1).abs())
     }
 
     pub fn can_access(&self, p1: (isize, isize, isize), p2: (isize, isize, isize)) -> bool {
         let t1 = self.get(p1.0, p1.1, p1.2);
         let t2 = self.get(p2.0, p2.1, p2.2);
-        if (p1.0, p1.1, p1.2) == (p2.0, p2.1, p1.2 + 1) {
-            if t1.is_bottom_stair_tile() && t2.is_top_stair_tile() {
-                return true;
-            }
+        let climbs = |bottom: GridTile, top: GridTile| {
+            (bottom.is_bottom_stair_tile() && top.is_top_stair_tile())
+                || (bottom == GridTile::LadderBottom && top == GridTile::LadderTop)
+        };
+        if p2 == (p1.0, p1.1, p1.2 + 1) && climbs(t1, t2) {
+            return true;
         }
-        if (p1.0, p1.1, p1.2) == (p2.0, p2.1, p1.2 - 1) {
-            if t2.is_bottom_stair_tile() && t1.is_top_stair_tile() {
-                return true;
-            }
+        if p2 == (p1.0, p1.1, p1.2 - 1) && climbs(t2, t1) {
+            return true;
         }
-        if (p1.2 != p2.2) {
+        if p1.2 != p2.2 {
             return false;
         }
 
@@ -187,20 +431,20 @@ impl IntoIterator for &LevelGrid {
     type IntoIter = GridIterator;
 
     fn into_iter(self) -> GridIterator {
-        GridIterator(0, 0, -1)
+        GridIterator(0, 0, -1, self.size())
     }
 }
 
-pub struct GridIterator(isize, isize, isize);
+pub struct GridIterator(isize, isize, isize, (usize, usize, usize));
 
 impl Iterator for GridIterator {
     type Item = (isize, isize, isize);
 
     fn next(&mut self) -> Option<Self::Item> {
         match (
-            self.0 - LEVEL_SIZE.0 as isize + 1,
-            self.1 - LEVEL_SIZE.1 as isize + 1,
-            self.2 - LEVEL_SIZE.2 as isize + 1,
+            self.0 - self.3 .0 as isize + 1,
+            self.1 - self.3 .1 as isize + 1,
+            self.2 - self.3 .2 as isize + 1,
         ) {
             (0, 0, 0) => None,
             (_, 0, 0) => {
@@ -0,0 +1,267 @@
+use super::config::{HeightMapSource, LevelGenConfig};
+use super::grid::HeightMap;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::fs::File;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+/// The heights a column can have, from open air up to the top storey.
+/// A column of height `h` holds tiles on storeys `h - 1` and `h`, so using every height gives every
+/// storey walkable tiles, and columns that differ by one storey can always be walked between.
+/// The lower buildings are the columns one below the top storey, see `LevelGrid::is_indoor`.
+fn height_levels(storeys: usize) -> Vec<u8> {
+    (0..storeys as u8).collect()
+}
+
+/// A grayscale image painted by a designer. Black is open air and brighter pixels are taller,
+/// spread evenly over the storeys so white is the top storey. The top row of the image is `y = 0`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct HeightMask {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl HeightMask {
+    pub fn load(path: impl AsRef<Path>) -> Result<HeightMask, Error> {
+        let mut decoder = png::Decoder::new(File::open(path)?);
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder
+            .read_info()
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader
+            .next_frame(&mut buffer)
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+
+        let channels = info.color_type.samples();
+        let pixels = buffer[..info.buffer_size()]
+            .chunks(info.line_size)
+            .flat_map(|line| line.chunks(channels).take(info.width as usize))
+            .map(|pixel| match pixel {
+                // Ignore the alpha channel and average the colors
+                [gray] | [gray, _] => *gray,
+                [red, green, blue] | [red, green, blue, _] => {
+                    ((*red as u16 + *green as u16 + *blue as u16) / 3) as u8
+                }
+                _ => 0,
+            })
+            .collect();
+
+        Ok(HeightMask {
+            width: info.width as usize,
+            height: info.height as usize,
+            pixels,
+        })
+    }
+
+    /// The brightness of the pixel covering tile `(x, y)` when the mask is stretched over the level
+    fn sample(&self, x: usize, y: usize, size: (usize, usize, usize)) -> u8 {
+        let pixel_x = x * self.width / size.0;
+        let pixel_y = y * self.height / size.1;
+
+        self.pixels[pixel_y * self.width + pixel_x]
+    }
+}
+
+/// A random number from `range`, or its start when the range is empty
+fn gen_range_or_start(rng: &mut StdRng, range: std::ops::Range<usize>) -> usize {
+    if range.is_empty() {
+        return range.start;
+    }
+    rng.gen_range(range)
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+struct Rectangle(usize, usize, usize, usize);
+
+impl Rectangle {
+    fn new_random(
+        parent: Rectangle,
+        rng: &mut StdRng,
+        min_size: usize,
+        max_size: usize,
+    ) -> Rectangle {
+        // In small levels the ranges can be empty, those use the minimum size and the parent corner
+        let size = (
+            gen_range_or_start(rng, min_size.min(parent.2)..max_size.min(parent.2)),
+            gen_range_or_start(rng, min_size.min(parent.3)..max_size.min(parent.3)),
+        );
+
+        let position = (
+            gen_range_or_start(rng, parent.0..parent.0 + parent.2 - size.0),
+            gen_range_or_start(rng, parent.1..parent.1 + parent.3 - size.1),
+        );
+
+        return Rectangle(position.0, position.1, size.0, size.1);
+    }
+
+    fn is_inside(&self, position: (usize, usize)) -> bool {
+        self.0 <= position.0
+            && position.0 < self.0 + self.2
+            && self.1 <= position.1
+            && position.1 < self.1 + self.3
+    }
+
+    /// How many tiles `position` is away from the rectangle, counting diagonal steps as one
+    fn distance(&self, position: (usize, usize)) -> usize {
+        let dx = (self.0.saturating_sub(position.0))
+            .max((position.0 + 1).saturating_sub(self.0 + self.2));
+        let dy = (self.1.saturating_sub(position.1))
+            .max((position.1 + 1).saturating_sub(self.1 + self.3));
+
+        dx.max(dy)
+    }
+}
+
+/// The outer rectangles reach up to the second highest storey, the inner one to the top storey.
+/// Around the outer rectangles the height goes down a storey per tile, so levels with more than
+/// three storeys get terraces leading up to the buildings.
+/// Rectangle sizes are based on the shorter side so they always fit inside the level.
+fn rectangle_height_map(size: (usize, usize, usize), rng: &mut StdRng) -> HeightMap {
+    let base_size = size.0.min(size.1);
+
+    let outer_rect_1 = Rectangle::new_random(
+        Rectangle(0, 0, size.0, size.1),
+        rng,
+        base_size / 2,
+        base_size * 3 / 4,
+    );
+
+    let outer_rect_2 = Rectangle::new_random(
+        Rectangle(0, 0, size.0, size.1),
+        rng,
+        base_size / 2,
+        base_size * 3 / 4,
+    );
+
+    let inner_rect = Rectangle::new_random(outer_rect_1, rng, base_size / 5, base_size / 2);
+
+    (0..size.0)
+        .map(|x| {
+            (0..size.1)
+                .map(|y| {
+                    if inner_rect.is_inside((x, y)) {
+                        size.2 as u8 - 1
+                    } else {
+                        let distance = outer_rect_1
+                            .distance((x, y))
+                            .min(outer_rect_2.distance((x, y)));
+                        (size.2 - 2).saturating_sub(distance) as u8
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Smooth random values between 0 and 1, made by interpolating a coarse grid of random values.
+/// The coarse grid has a point every `cell_size` tiles.
+fn value_noise(size: (usize, usize, usize), cell_size: usize, rng: &mut StdRng) -> Vec<Vec<f32>> {
+    let lattice: Vec<Vec<f32>> = (0..size.0 / cell_size + 2)
+        .map(|_| (0..size.1 / cell_size + 2).map(|_| rng.gen()).collect())
+        .collect();
+
+    let smooth_step = |t: f32| t * t * (3. - 2. * t);
+
+    (0..size.0)
+        .map(|x| {
+            (0..size.1)
+                .map(|y| {
+                    let (cell_x, cell_y) = (x / cell_size, y / cell_size);
+                    let tx = smooth_step((x % cell_size) as f32 / cell_size as f32);
+                    let ty = smooth_step((y % cell_size) as f32 / cell_size as f32);
+
+                    let top =
+                        lattice[cell_x][cell_y] * (1. - tx) + lattice[cell_x + 1][cell_y] * tx;
+                    let bottom = lattice[cell_x][cell_y + 1] * (1. - tx)
+                        + lattice[cell_x + 1][cell_y + 1] * tx;
+                    top * (1. - ty) + bottom * ty
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Thresholded noise, giving blobs of buildings with irregular and L-shaped outlines.
+/// Below 0.45 is open air, above that every storey gets a slice of `0.5 / (storeys - 1)`
+/// and the top storey everything that is left, so with three storeys it starts at 0.7.
+fn noise_height_map(size: (usize, usize, usize), rng: &mut StdRng) -> HeightMap {
+    let levels = height_levels(size.2);
+    let noise = value_noise(size, (size.0.min(size.1) / 3).max(2), rng);
+    let step = 0.5 / (levels.len() - 1) as f32;
+
+    noise
+        .into_iter()
+        .map(|column| {
+            column
+                .into_iter()
+                .map(|value| {
+                    if value < 0.45 {
+                        return levels[0];
+                    }
+                    let level = 1 + ((value - 0.45) / step) as usize;
+                    levels[level.min(levels.len() - 1)]
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Splits the level into districts around random points, each with its own height
+fn voronoi_height_map(size: (usize, usize, usize), rng: &mut StdRng) -> HeightMap {
+    let levels = height_levels(size.2);
+    let district_count = (size.0 * size.1 / 24).max(3);
+    let districts: Vec<((usize, usize), u8)> = (0..district_count)
+        .map(|_| {
+            (
+                (rng.gen_range(0..size.0), rng.gen_range(0..size.1)),
+                *levels.choose(rng).unwrap(),
+            )
+        })
+        .collect();
+
+    (0..size.0)
+        .map(|x| {
+            (0..size.1)
+                .map(|y| {
+                    districts
+                        .iter()
+                        .min_by_key(|((district_x, district_y), _)| {
+                            let dx = x as isize - *district_x as isize;
+                            let dy = y as isize - *district_y as isize;
+                            dx * dx + dy * dy
+                        })
+                        .map(|(_, height)| *height)
+                        .unwrap()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn mask_height_map(size: (usize, usize, usize), mask: &HeightMask) -> HeightMap {
+    let levels = height_levels(size.2);
+
+    (0..size.0)
+        .map(|x| {
+            (0..size.1)
+                .map(|y| levels[mask.sample(x, y, size) as usize * levels.len() / 256])
+                .collect()
+        })
+        .collect()
+}
+
+/// The maximum height of every column, made with the strategy picked in the config
+pub fn generate_height_map(config: &LevelGenConfig, rng: &mut StdRng) -> HeightMap {
+    let size = config.dimensions.size;
+
+    match &config.height_map {
+        HeightMapSource::Rectangles => rectangle_height_map(size, rng),
+        HeightMapSource::Noise => noise_height_map(size, rng),
+        HeightMapSource::Voronoi => voronoi_height_map(size, rng),
+        HeightMapSource::Mask(mask) => mask_height_map(size, mask),
+    }
+}
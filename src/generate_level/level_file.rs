@@ -0,0 +1,218 @@
+use super::ascii::parse_grid;
+use super::grid::{DoorEdge, GridTile, HeightMap, LevelDimensions, LevelGrid, Lock, Room};
+use serde::{Deserialize, Serialize};
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+/// The on-disk form of a `LevelGrid`.
+/// Tiles are indexed as `tiles[x][y][z]` and the height map as `max_height[x][y]`.
+#[derive(Serialize, Deserialize)]
+struct LevelFile {
+    dimensions: LevelDimensions,
+    max_height: HeightMap,
+    tiles: Vec<Vec<Vec<GridTile>>>,
+    /// Missing in files saved before there were doors
+    #[serde(default)]
+    doors: Vec<DoorEdge>,
+    #[serde(default)]
+    locks: Vec<Lock>,
+    #[serde(default)]
+    rooms: Vec<Room>,
+}
+
+impl From<&LevelGrid> for LevelFile {
+    fn from(grid: &LevelGrid) -> LevelFile {
+        let size = grid.size();
+
+        LevelFile {
+            dimensions: grid.dimensions(),
+            max_height: grid.max_height.clone(),
+            tiles: (0..size.0 as isize)
+                .map(|x| {
+                    (0..size.1 as isize)
+                        .map(|y| (0..size.2 as isize).map(|z| grid.get(x, y, z)).collect())
+                        .collect()
+                })
+                .collect(),
+            doors: grid.doors().to_vec(),
+            locks: grid.locks().to_vec(),
+            rooms: grid.rooms().to_vec(),
+        }
+    }
+}
+
+impl TryFrom<LevelFile> for LevelGrid {
+    type Error = Error;
+
+    fn try_from(file: LevelFile) -> Result<LevelGrid, Error> {
+        let size = file.dimensions.size;
+
+        if file.max_height.len() != size.0
+            || file.max_height.iter().any(|column| column.len() != size.1)
+        {
+            return Err(invalid_data("height map does not match the level size"));
+        }
+
+        if file.tiles.len() != size.0
+            || file.tiles.iter().any(|column| {
+                column.len() != size.1 || column.iter().any(|tiles| tiles.len() != size.2)
+            })
+        {
+            return Err(invalid_data("tiles do not match the level size"));
+        }
+
+        let mut grid = LevelGrid::new(file.dimensions, file.max_height);
+        for (x, y, z) in &grid {
+            let tile = file.tiles[x as usize][y as usize][z as usize];
+            if tile == GridTile::OutOfBounds {
+                return Err(invalid_data("tiles can not be out of bounds"));
+            }
+            grid.set(x, y, z, tile);
+        }
+        for (p1, p2) in file.doors {
+            grid.add_door(p1, p2);
+        }
+        for lock in file.locks {
+            grid.add_lock(lock.door.0, lock.door.1, lock.key);
+        }
+        for room in file.rooms {
+            grid.add_room(room);
+        }
+
+        return Ok(grid);
+    }
+}
+
+fn invalid_data(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+pub fn level_to_string(grid: &LevelGrid) -> Result<String, Error> {
+    ron::ser::to_string_pretty(
+        &LevelFile::from(grid),
+        ron::ser::PrettyConfig::new().depth_limit(2),
+    )
+    .map_err(|err| Error::new(ErrorKind::InvalidData, err))
+}
+
+pub fn level_from_str(text: &str) -> Result<LevelGrid, Error> {
+    let file: LevelFile =
+        ron::from_str(text).map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+
+    LevelGrid::try_from(file)
+}
+
+/// The format a level file is written in, picked from its extension
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum LevelFormat {
+    Ron,
+    Json,
+    Ascii,
+}
+
+impl LevelFormat {
+    /// `.txt` files get the ASCII map, `.json` files JSON and anything else RON
+    pub fn from_path(path: &Path) -> LevelFormat {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("txt") => LevelFormat::Ascii,
+            Some("json") => LevelFormat::Json,
+            _ => LevelFormat::Ron,
+        }
+    }
+}
+
+pub fn level_to_json(grid: &LevelGrid) -> Result<String, Error> {
+    serde_json::to_string(&LevelFile::from(grid)).map_err(Error::from)
+}
+
+pub fn level_from_json(text: &str) -> Result<LevelGrid, Error> {
+    let file: LevelFile = serde_json::from_str(text).map_err(Error::from)?;
+
+    LevelGrid::try_from(file)
+}
+
+/// Write the grid to a file, so it can be rebuilt later with `load_level`
+pub fn save_level(grid: &LevelGrid, path: impl AsRef<Path>) -> Result<(), Error> {
+    let path = path.as_ref();
+    let text = match LevelFormat::from_path(path) {
+        LevelFormat::Ron => level_to_string(grid)?,
+        LevelFormat::Json => level_to_json(grid)?,
+        LevelFormat::Ascii => grid.to_string(),
+    };
+
+    std::fs::write(path, text)
+}
+
+pub fn load_level(path: impl AsRef<Path>) -> Result<LevelGrid, Error> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path)?;
+
+    match LevelFormat::from_path(path) {
+        LevelFormat::Ron => level_from_str(&text),
+        LevelFormat::Json => level_from_json(&text),
+        LevelFormat::Ascii => parse_grid(&text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Written by `generate_headless --size 4x4x3 --seed 1` while `LevelGrid` still had
+    /// positional fields
+    const OLD_RON: &str = "\
+(
+    dimensions: (
+        size: (4, 4, 3),
+        scale: (3.0, 3.0, 2.5),
+    ),
+    max_height: [
+        [1, 1, 0, 0],
+        [1, 1, 1, 0],
+        [0, 1, 1, 0],
+        [0, 0, 0, 0],
+    ],
+    tiles: [
+        [[LadderBottom, LadderTop, Empty], [Empty, Empty, Empty], [Empty, Empty, Empty], [Empty, Empty, Empty]],
+        [[Floor, Floor, Empty], [Floor, Floor, Empty], [Floor, Floor, Empty], [Empty, Empty, Empty]],
+        [[Floor, Empty, Empty], [Floor, Floor, Empty], [Floor, Floor, Empty], [Empty, Empty, Empty]],
+        [[Floor, Empty, Empty], [Floor, Empty, Empty], [Empty, Empty, Empty], [Empty, Empty, Empty]],
+    ],
+    doors: [
+        ((1, 0, 0), (2, 0, 0)),
+        ((2, 0, 0), (2, 1, 0)),
+        ((2, 1, 0), (3, 1, 0)),
+        ((1, 0, 1), (1, 1, 1)),
+    ],
+    locks: [
+        (door: ((1, 0, 1), (1, 1, 1)), key: (1, 2, 0)),
+    ],
+    rooms: [
+        (min: (1, 1), max: (2, 2), z: 0),
+    ],
+)";
+
+    const OLD_JSON: &str = r#"{"dimensions":{"size":[4,4,3],"scale":[3.0,3.0,2.5]},"max_height":[[1,1,0,0],[1,1,1,0],[0,1,1,0],[0,0,0,0]],"tiles":[[["LadderBottom","LadderTop","Empty"],["Empty","Empty","Empty"],["Empty","Empty","Empty"],["Empty","Empty","Empty"]],[["Floor","Floor","Empty"],["Floor","Floor","Empty"],["Floor","Floor","Empty"],["Empty","Empty","Empty"]],[["Floor","Empty","Empty"],["Floor","Floor","Empty"],["Floor","Floor","Empty"],["Empty","Empty","Empty"]],[["Floor","Empty","Empty"],["Floor","Empty","Empty"],["Empty","Empty","Empty"],["Empty","Empty","Empty"]]],"doors":[[[1,0,0],[2,0,0]],[[2,0,0],[2,1,0]],[[2,1,0],[3,1,0]],[[1,0,1],[1,1,1]]],"locks":[{"door":[[1,0,1],[1,1,1]],"key":[1,2,0]}],"rooms":[{"min":[1,1],"max":[2,2],"z":0}]}"#;
+
+    #[test]
+    fn files_written_before_named_fields_still_load() {
+        let grid = level_from_str(OLD_RON).unwrap();
+
+        assert_eq!(grid.size(), (4, 4, 3));
+        assert_eq!(grid.get(0, 0, 0), GridTile::LadderBottom);
+        assert_eq!(grid.get_max_height(1, 2), 1);
+        assert_eq!(grid.doors().len(), 4);
+        assert_eq!(grid.lock_between((1, 1, 1), (1, 0, 1)), Some(0));
+        assert_eq!(grid.locks()[0].key, (1, 2, 0));
+        assert_eq!(grid.rooms().len(), 1);
+        assert_eq!(level_from_json(OLD_JSON).unwrap(), grid);
+    }
+
+    #[test]
+    fn files_are_written_in_the_same_format_as_before() {
+        let grid = level_from_str(OLD_RON).unwrap();
+
+        assert_eq!(level_to_string(&grid).unwrap(), OLD_RON);
+        assert_eq!(level_to_json(&grid).unwrap(), OLD_JSON);
+    }
+}
@@ -0,0 +1,120 @@
+use super::grid::{GridTile, LevelGrid};
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::collections::{HashSet, VecDeque};
+
+/// Locks are at least this many steps from the start and from each other along the critical path
+const MIN_LOCK_SPACING: usize = 4;
+
+impl LevelGrid {
+    /// Every tile that can be walked to from `start`, without taking the steps `is_blocked` rejects
+    fn reachable_without(
+        &self,
+        start: (isize, isize, isize),
+        is_blocked: impl Fn((isize, isize, isize), (isize, isize, isize)) -> bool,
+    ) -> HashSet<(isize, isize, isize)> {
+        let mut visited = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+
+        while let Some(position) = queue.pop_front() {
+            for next in self.neighbours(position) {
+                if !is_blocked(position, next) && visited.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        visited
+    }
+
+    /// Walk from `start` picking up every key that can be reached, and opening the doors they
+    /// belong to, until `goal` can be reached or there are no more keys to find.
+    /// Returns the indices of the locks in the order they were opened,
+    /// or `None` when the goal can not be reached.
+    pub fn solve_locks(
+        &self,
+        start: (isize, isize, isize),
+        goal: (isize, isize, isize),
+    ) -> Option<Vec<usize>> {
+        let mut opened = vec![];
+
+        loop {
+            let reachable = self.reachable_without(start, |from, to| {
+                self.lock_between(from, to)
+                    .is_some_and(|lock| !opened.contains(&lock))
+            });
+            if reachable.contains(&goal) {
+                return Some(opened);
+            }
+
+            let found: Vec<usize> = (0..self.locks().len())
+                .filter(|lock| !opened.contains(lock))
+                .filter(|&lock| reachable.contains(&self.locks()[lock].key))
+                .collect();
+            if found.is_empty() {
+                return None;
+            }
+            opened.extend(found);
+        }
+    }
+}
+
+/// Put up to `count` locked doors on the critical path, each with its key somewhere that can be
+/// reached before getting to it, but not on the critical path itself.
+/// A lock is only placed where there is no way around it, so every key is needed, and only in
+/// a doorway of a building or on a door that is already there, so the walls keep the player
+/// from walking around the door.
+/// Returns the number of locks added.
+pub fn add_locks(grid: &mut LevelGrid, count: usize, rng: &mut StdRng) -> usize {
+    let objective = match grid.find_objective() {
+        Some(objective) => objective,
+        None => return 0,
+    };
+    let critical_path: HashSet<_> = objective.critical_path.iter().copied().collect();
+
+    let mut added = 0;
+    let mut last_lock = 0;
+    for (index, pair) in objective.critical_path.windows(2).enumerate() {
+        if added == count {
+            break;
+        }
+
+        let (p1, p2) = (pair[0], pair[1]);
+        if index < last_lock + MIN_LOCK_SPACING
+            || p1.2 != p2.2
+            || grid.get(p1.0, p1.1, p1.2) != GridTile::Floor
+            || grid.get(p2.0, p2.1, p2.2) != GridTile::Floor
+            || !(grid.has_door(p1, p2) || grid.is_indoor(p1.0, p1.1) != grid.is_indoor(p2.0, p2.1))
+        {
+            continue;
+        }
+
+        // Earlier locks can be opened by then, their keys are found before them
+        let before_lock = grid.reachable_without(objective.start, |from, to| {
+            (from, to) == (p1, p2) || (from, to) == (p2, p1)
+        });
+        if before_lock.contains(&objective.goal) {
+            continue;
+        }
+
+        let mut key_tiles: Vec<_> = before_lock
+            .into_iter()
+            .filter(|&(x, y, z)| {
+                grid.get(x, y, z) == GridTile::Floor
+                    && !critical_path.contains(&(x, y, z))
+                    && !grid.locks().iter().any(|lock| lock.key == (x, y, z))
+            })
+            .collect();
+        // Sets are iterated in a random order, which would make the same seed give another level
+        key_tiles.sort();
+
+        if let Some(&key) = key_tiles.choose(rng) {
+            let lock = grid.add_lock(p1, p2, key);
+            println!("Added lock {lock} between {p1:?} and {p2:?} with its key at {key:?}");
+            added += 1;
+            last_lock = index;
+        }
+    }
+
+    added
+}
@@ -0,0 +1,135 @@
+use super::grid::{GridTile, LevelGrid, DIRECTIONS};
+use super::pathfinding::DistanceField;
+use std::collections::HashMap;
+
+/// Two tiles have to be at least this many steps apart before a loop between them is worth it
+const MIN_LOOP_DISTANCE: usize = 6;
+
+const BOTTOM_STAIR_TILES: [GridTile; 4] = [
+    GridTile::StairsEastBottom,
+    GridTile::StairsNorthBottom,
+    GridTile::StairsWestBottom,
+    GridTile::StairsSouthBottom,
+];
+
+/// Tiles that would join the walkable tiles at `ends` if they were placed
+struct LoopCandidate {
+    ends: ((isize, isize, isize), (isize, isize, isize)),
+    tiles: Vec<((isize, isize, isize), GridTile)>,
+}
+
+/// Whether the tile at `position` is walkable and lets you walk in `direction`
+fn opens_towards(
+    grid: &LevelGrid,
+    position: (isize, isize, isize),
+    direction: (isize, isize),
+) -> bool {
+    let tile = grid.get(position.0, position.1, position.2);
+    tile.is_walkable() && tile.can_access(direction)
+}
+
+fn is_free(grid: &LevelGrid, position: (isize, isize, isize)) -> bool {
+    grid.get(position.0, position.1, position.2) == GridTile::Empty
+        && grid.is_valid_height(position.0, position.1, position.2)
+}
+
+fn has_stair_next_to(grid: &LevelGrid, (x, y, z): (isize, isize, isize)) -> bool {
+    DIRECTIONS
+        .iter()
+        .any(|(dx, dy)| grid.get(x + dx, y + dy, z).is_stair_tile())
+}
+
+/// Every empty tile that can be filled with a floor or a stair to connect two walkable tiles
+fn find_candidates(grid: &LevelGrid) -> Vec<LoopCandidate> {
+    let mut candidates = vec![];
+
+    for (x, y, z) in grid {
+        if !is_free(grid, (x, y, z)) {
+            continue;
+        }
+
+        // A floor tile joining two of its neighbours on the same storey
+        let open_neighbours: Vec<_> = DIRECTIONS
+            .iter()
+            .map(|&(dx, dy)| (x + dx, y + dy, z))
+            .filter(|&(nx, ny, nz)| opens_towards(grid, (nx, ny, nz), (x - nx, y - ny)))
+            .collect();
+        for (index, &first) in open_neighbours.iter().enumerate() {
+            for &second in &open_neighbours[index + 1..] {
+                candidates.push(LoopCandidate {
+                    ends: (first, second),
+                    tiles: vec![((x, y, z), GridTile::Floor)],
+                });
+            }
+        }
+
+        // A stair going up from a tile on this storey to a tile on the storey above
+        if !is_free(grid, (x, y, z + 1))
+            || has_stair_next_to(grid, (x, y, z))
+            || has_stair_next_to(grid, (x, y, z + 1))
+        {
+            continue;
+        }
+        for (dx, dy) in DIRECTIONS {
+            let bottom_end = (x - dx, y - dy, z);
+            let top_end = (x + dx, y + dy, z + 1);
+            if !opens_towards(grid, bottom_end, (dx, dy))
+                || !opens_towards(grid, top_end, (-dx, -dy))
+            {
+                continue;
+            }
+
+            let bottom = BOTTOM_STAIR_TILES
+                .into_iter()
+                .find(|tile| tile.can_access((-dx, -dy)))
+                .unwrap();
+            candidates.push(LoopCandidate {
+                ends: (bottom_end, top_end),
+                tiles: vec![
+                    ((x, y, z), bottom),
+                    ((x, y, z + 1), bottom.get_opposite_stair_tile().unwrap()),
+                ],
+            });
+        }
+    }
+
+    candidates
+}
+
+/// Join parts of the level that are next to each other but far apart when walking,
+/// so players do not have to walk back the way they came as often.
+/// Every loop joins the two tiles that are the furthest apart. Returns the number of loops added,
+/// which is less than `count` when there are no more tiles far enough apart.
+pub fn add_loops(grid: &mut LevelGrid, count: usize) -> usize {
+    for added in 0..count {
+        let mut distance_fields: HashMap<(isize, isize, isize), DistanceField> = HashMap::new();
+
+        let best = find_candidates(grid)
+            .into_iter()
+            .filter_map(|candidate| {
+                let (start, end) = candidate.ends;
+                let distances = distance_fields
+                    .entry(start)
+                    .or_insert_with(|| grid.distance_field(start));
+                let distance = distances[end.0 as usize][end.1 as usize][end.2 as usize]?;
+
+                (distance >= MIN_LOOP_DISTANCE).then_some((distance, candidate))
+            })
+            .max_by_key(|(distance, _)| *distance);
+
+        match best {
+            Some((distance, candidate)) => {
+                println!(
+                    "Added a loop between {:?} that were {distance} steps apart",
+                    candidate.ends
+                );
+                for ((x, y, z), tile) in candidate.tiles {
+                    grid.set(x, y, z, tile);
+                }
+            }
+            None => return added,
+        }
+    }
+
+    count
+}
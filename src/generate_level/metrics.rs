@@ -0,0 +1,134 @@
+use super::config::ScoreWeights;
+use super::generator::GenerationStats;
+use super::grid::LevelGrid;
+use std::collections::HashSet;
+use std::fmt;
+
+/// Numbers that describe the layout of a level, to compare levels without walking through them
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct LevelMetrics {
+    /// The number of floor and stair tiles on every storey, from the bottom up
+    pub walkable_per_storey: Vec<usize>,
+    /// The number of stairs, each made of a top and a bottom tile
+    pub stairs: usize,
+    /// Tiles where the path splits, those with three or more neighbours
+    pub branches: usize,
+    /// The most steps needed to walk between any two tiles of the level.
+    /// Only measured when asked for, because it walks from every tile.
+    pub longest_path: Option<usize>,
+    /// The number of independent loops, so the number of paths that could be removed
+    /// without splitting the level
+    pub loops: usize,
+    pub dead_ends_removed: usize,
+    /// The part of the walkable tiles that is inside a building
+    pub indoor_ratio: f32,
+}
+
+impl LevelMetrics {
+    /// Measure the level, with `longest_path` only when `with_longest_path` is set
+    pub fn measure(
+        grid: &LevelGrid,
+        stats: &GenerationStats,
+        with_longest_path: bool,
+    ) -> LevelMetrics {
+        let walkable: Vec<_> = grid
+            .into_iter()
+            .filter(|&(x, y, z)| grid.get(x, y, z).is_walkable())
+            .collect();
+
+        let mut walkable_per_storey = vec![0; grid.size().2];
+        let mut stairs = 0;
+        let mut branches = 0;
+        let mut connections = 0;
+        let mut indoor = 0;
+        for &(x, y, z) in &walkable {
+            walkable_per_storey[z as usize] += 1;
+
+            if grid.get(x, y, z).is_bottom_stair_tile() {
+                stairs += 1;
+            }
+
+            let neighbours = grid.neighbours((x, y, z)).count();
+            connections += neighbours;
+            if neighbours >= 3 {
+                branches += 1;
+            }
+
+            if grid.is_indoor(x, y) {
+                indoor += 1;
+            }
+        }
+
+        let mut visited = HashSet::new();
+        let mut components = 0;
+        for &position in &walkable {
+            if visited.insert(position) {
+                components += 1;
+                visited.extend(grid.reachable(position));
+            }
+        }
+
+        let longest_path = with_longest_path.then(|| {
+            walkable
+                .iter()
+                .flat_map(|&position| {
+                    grid.distance_field(position)
+                        .into_iter()
+                        .flatten()
+                        .flatten()
+                        .flatten()
+                })
+                .max()
+                .unwrap_or(0)
+        });
+
+        LevelMetrics {
+            walkable_per_storey,
+            stairs,
+            branches,
+            longest_path,
+            // Every connection is counted from both of its tiles
+            loops: (connections / 2 + components).saturating_sub(walkable.len()),
+            dead_ends_removed: stats.dead_ends_removed,
+            indoor_ratio: if walkable.is_empty() {
+                0.
+            } else {
+                indoor as f32 / walkable.len() as f32
+            },
+        }
+    }
+
+    pub fn walkable(&self) -> usize {
+        self.walkable_per_storey.iter().sum()
+    }
+
+    /// All metrics multiplied by their weight and added up
+    pub fn score(&self, weights: &ScoreWeights) -> f32 {
+        self.walkable() as f32 * weights.walkable
+            + self.stairs as f32 * weights.stairs
+            + self.branches as f32 * weights.branches
+            + self.longest_path.unwrap_or(0) as f32 * weights.longest_path
+            + self.loops as f32 * weights.loops
+            + self.dead_ends_removed as f32 * weights.dead_ends_removed
+            + self.indoor_ratio * weights.indoor_ratio
+    }
+}
+
+impl fmt::Display for LevelMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "walkable tiles: {} {:?}",
+            self.walkable(),
+            self.walkable_per_storey
+        )?;
+        writeln!(f, "stairs: {}", self.stairs)?;
+        writeln!(f, "branches: {}", self.branches)?;
+        if let Some(longest_path) = self.longest_path {
+            writeln!(f, "longest path: {longest_path}")?;
+        }
+        writeln!(f, "loops: {}", self.loops)?;
+        writeln!(f, "dead ends removed: {}", self.dead_ends_removed)?;
+        writeln!(f, "indoor: {:.0}%", self.indoor_ratio * 100.)
+    }
+}
@@ -1,28 +1,61 @@
-use bevy::prelude::*;
-
+mod ascii;
+mod config;
+#[cfg(feature = "bevy")]
 mod decorator;
+mod doors;
 mod generator;
 mod grid;
+mod height_map;
+mod level_file;
+mod locks;
+mod loops;
+mod metrics;
+mod objective;
+mod pathfinding;
+#[cfg(feature = "bevy")]
+mod plugin;
+mod random_walk;
+mod rooms;
+mod seed;
+#[cfg(feature = "bevy")]
 mod shared_models;
+mod transform;
+mod validate;
+mod vignettes;
+#[cfg(feature = "bevy")]
+mod visualizer;
+mod wave_function_collapse;
 
-pub fn generate_level(
-    commands: &mut Commands,
-    meshes: &mut ResMut<Assets<Mesh>>,
-    materials: &mut ResMut<Assets<StandardMaterial>>,
-    asset_server: Res<AssetServer>,
-) {
-    let grid = generator::generate_level_grid();
-    decorator::decorate_level(&grid, commands, materials, asset_server);
-
-    let floor_plane = meshes.add(Mesh::from(shape::Plane { size: 3. * 15.0 }));
-    commands.spawn_bundle(PbrBundle {
-        mesh: floor_plane,
-        transform: Transform::from_xyz(6.0 * 3.0, -2.5, 6.0 * 3.0),
-        material: materials.add(StandardMaterial {
-            base_color: Color::rgb(0., 0., 0.),
-            perceptual_roughness: 1.0,
-            ..default()
-        }),
-        ..default()
-    });
-}
+pub use ascii::parse_grid;
+pub use config::{GeneratorAlgorithm, HeightMapSource, LevelGenConfig, ScoreWeights};
+#[cfg(feature = "bevy")]
+pub use decorator::{Climbable, Door, DoorKey, LevelPiece, LevelRoot, TileCoord};
+pub use doors::add_doors;
+pub use generator::{
+    generate_level, generate_level_grid, generate_level_with, try_generate_level,
+    try_generate_level_with, GeneratedLevel, GenerationStats, LevelGenerator, PhaseTimes,
+    MAX_ATTEMPTS,
+};
+pub use grid::{
+    DoorEdge, GenerationStep, GridTile, HeightMap, LevelDimensions, LevelGrid, Lock, Room,
+};
+pub use height_map::{generate_height_map, HeightMask};
+pub use level_file::{load_level, save_level};
+pub use locks::add_locks;
+pub use loops::add_loops;
+pub use metrics::LevelMetrics;
+pub use objective::LevelObjective;
+pub use pathfinding::DistanceField;
+#[cfg(feature = "bevy")]
+pub use plugin::{
+    CurrentLevel, LevelDecorated, LevelGenerated, LevelGeneratorPlugin, RegenerateLevel,
+};
+pub use random_walk::RandomWalkGenerator;
+pub use rooms::add_rooms;
+pub use seed::LevelSeed;
+pub use transform::Axis;
+pub use validate::{ValidationReport, Violation};
+pub use vignettes::{add_vignettes, Connection, VignetteTemplate};
+#[cfg(feature = "bevy")]
+pub use visualizer::GenerationReplay;
+pub use wave_function_collapse::WaveFunctionCollapseGenerator;
@@ -0,0 +1,45 @@
+use super::grid::{GridTile, LevelGrid};
+use super::pathfinding::DistanceField;
+
+/// Where the player starts, where they have to go, and the shortest way between the two
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LevelObjective {
+    pub start: (isize, isize, isize),
+    pub goal: (isize, isize, isize),
+    /// The tiles from `start` to `goal`, both included
+    pub critical_path: Vec<(isize, isize, isize)>,
+}
+
+impl LevelObjective {
+    /// The number of steps needed to get from the start to the goal
+    pub fn length(&self) -> usize {
+        self.critical_path.len() - 1
+    }
+}
+
+impl LevelGrid {
+    /// The floor tile that is the most steps away according to `distances`
+    fn furthest_floor(&self, distances: &DistanceField) -> Option<((isize, isize, isize), usize)> {
+        self.into_iter()
+            .filter(|&(x, y, z)| self.get(x, y, z) == GridTile::Floor)
+            .filter_map(|(x, y, z)| {
+                distances[x as usize][y as usize][z as usize].map(|distance| ((x, y, z), distance))
+            })
+            .max_by_key(|(_, distance)| *distance)
+    }
+
+    /// Pick two floor tiles that are about as far apart as possible as the start and goal.
+    /// The goal is the floor furthest from the spawn tile,
+    /// and the start the floor furthest from the goal.
+    pub fn find_objective(&self) -> Option<LevelObjective> {
+        let spawn_tile = self.find_spawn_tile()?;
+        let (goal, _) = self.furthest_floor(&self.distance_field(spawn_tile))?;
+        let (start, _) = self.furthest_floor(&self.distance_field(goal))?;
+
+        Some(LevelObjective {
+            start,
+            goal,
+            critical_path: self.find_path(start, goal)?,
+        })
+    }
+}
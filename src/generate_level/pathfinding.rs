@@ -0,0 +1,163 @@
+use super::grid::{LevelGrid, DIRECTIONS};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+
+/// The number of steps from a source to every tile, indexed as `[x][y][z]`.
+/// Tiles that can not be reached are `None`.
+pub type DistanceField = Vec<Vec<Vec<Option<usize>>>>;
+
+impl LevelGrid {
+    /// Every tile that can be walked to in a single step from `position`,
+    /// going up or down stairs counts as a single step.
+    pub fn neighbours(
+        &self,
+        position: (isize, isize, isize),
+    ) -> impl Iterator<Item = (isize, isize, isize)> + '_ {
+        let (x, y, z) = position;
+
+        DIRECTIONS
+            .iter()
+            .map(move |&(dx, dy)| (x + dx, y + dy, z))
+            .chain([(x, y, z + 1), (x, y, z - 1)])
+            .filter(move |&next| self.can_access(position, next))
+    }
+
+    /// Breadth first search from `source`, giving the number of steps to every tile
+    pub fn distance_field(&self, source: (isize, isize, isize)) -> DistanceField {
+        let size = self.size();
+        let mut distances = vec![vec![vec![None; size.2]; size.1]; size.0];
+
+        if self.get(source.0, source.1, source.2).is_walkable() {
+            distances[source.0 as usize][source.1 as usize][source.2 as usize] = Some(0);
+        } else {
+            return distances;
+        }
+
+        let mut queue = VecDeque::from([(source, 0)]);
+        while let Some((position, distance)) = queue.pop_front() {
+            for (x, y, z) in self.neighbours(position) {
+                let cell = &mut distances[x as usize][y as usize][z as usize];
+                if cell.is_none() {
+                    *cell = Some(distance + 1);
+                    queue.push_back(((x, y, z), distance + 1));
+                }
+            }
+        }
+
+        return distances;
+    }
+
+    /// Every tile that can be walked to from `source`, including `source` itself
+    pub fn reachable(&self, source: (isize, isize, isize)) -> HashSet<(isize, isize, isize)> {
+        let distances = self.distance_field(source);
+
+        self.into_iter()
+            .filter(|&(x, y, z)| distances[x as usize][y as usize][z as usize].is_some())
+            .collect()
+    }
+
+    /// The shortest list of tiles leading from `start` to `goal`, both included.
+    /// Uses A* with the manhattan distance, since every step costs the same.
+    pub fn find_path(
+        &self,
+        start: (isize, isize, isize),
+        goal: (isize, isize, isize),
+    ) -> Option<Vec<(isize, isize, isize)>> {
+        if !self.get(start.0, start.1, start.2).is_walkable()
+            || !self.get(goal.0, goal.1, goal.2).is_walkable()
+        {
+            return None;
+        }
+
+        let heuristic = |(x, y, z): (isize, isize, isize)| {
+            ((x - goal.0).abs() + (y - goal.1).abs() + (z - goal.2).abs()) as usize
+        };
+
+        let mut came_from = HashMap::new();
+        let mut cost = HashMap::from([(start, 0)]);
+        let mut open = BinaryHeap::from([Reverse((heuristic(start), start))]);
+
+        while let Some(Reverse((_, position))) = open.pop() {
+            if position == goal {
+                let mut path = vec![goal];
+                while let Some(&previous) = came_from.get(path.last().unwrap()) {
+                    path.push(previous);
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            let next_cost = cost[&position] + 1;
+            for next in self.neighbours(position) {
+                if cost.get(&next).is_none_or(|&old_cost| next_cost < old_cost) {
+                    cost.insert(next, next_cost);
+                    came_from.insert(next, position);
+                    open.push(Reverse((next_cost + heuristic(next), next)));
+                }
+            }
+        }
+
+        return None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generate_level::ascii::parse_grid;
+
+    #[test]
+    fn path_goes_up_stairs() {
+        let grid = parse_grid(
+            "4x1x2
+            z=0
+            #e#.  1111
+            z=1
+            #E..  1111",
+        )
+        .unwrap();
+
+        assert_eq!(
+            grid.find_path((2, 0, 0), (0, 0, 1)),
+            Some(vec![(2, 0, 0), (1, 0, 0), (1, 0, 1), (0, 0, 1)])
+        );
+        // The stair can only be walked onto from the side it faces
+        assert_eq!(grid.find_path((0, 0, 0), (0, 0, 1)), None);
+    }
+
+    #[test]
+    fn unreachable_goal() {
+        let grid = parse_grid(
+            "4x1x1
+            z=0
+            ##.#  0000",
+        )
+        .unwrap();
+
+        assert_eq!(grid.find_path((0, 0, 0), (3, 0, 0)), None);
+        assert_eq!(grid.distance_field((0, 0, 0))[3][0][0], None);
+        assert_eq!(
+            grid.reachable((0, 0, 0)),
+            [(0, 0, 0), (1, 0, 0)].into_iter().collect()
+        );
+    }
+
+    #[test]
+    fn distances_over_a_ladder() {
+        let grid = parse_grid(
+            "3x1x2
+            z=0
+            #l.  111
+            z=1
+            .L#  111",
+        )
+        .unwrap();
+        let distances = grid.distance_field((0, 0, 0));
+
+        assert_eq!(distances[0][0][0], Some(0));
+        assert_eq!(distances[1][0][0], Some(1));
+        assert_eq!(distances[1][0][1], Some(2));
+        assert_eq!(distances[2][0][1], Some(3));
+        assert_eq!(distances[2][0][0], None);
+        assert_eq!(distances[0][0][1], None);
+    }
+}
@@ -0,0 +1,234 @@
+use super::config::LevelGenConfig;
+use super::decorator::{LevelPiece, LevelRoot};
+use super::grid::LevelGrid;
+use super::level_file::{load_level, save_level};
+use super::objective::LevelObjective;
+use super::seed::LevelSeed;
+use super::{decorator, generator, visualizer};
+use bevy::prelude::*;
+use rand::prelude::*;
+
+const REGENERATE_KEY: KeyCode = KeyCode::R;
+
+/// The grid of the level that is currently in the world
+pub struct CurrentLevel(pub Box<LevelGrid>);
+
+/// Sent once the grid has been generated or loaded, and is available as `CurrentLevel`
+pub struct LevelGenerated;
+
+/// Sent once every entity of the level has been spawned
+pub struct LevelDecorated {
+    /// The tile the player starts on. This is the start of the new `LevelObjective`,
+    /// which only replaces the old resource once the commands of the stage are applied.
+    pub start: Option<(isize, isize, isize)>,
+}
+
+/// Send this to throw away the current level and build a new one.
+/// Without a seed a random one is picked.
+pub struct RegenerateLevel {
+    pub seed: Option<u64>,
+}
+
+/// Generates a level from the `LevelGenConfig` resource on startup and decorates it.
+/// Set `load_path` in the config to use a saved level instead, and `save_path` to keep the one
+/// that was built.
+/// Press R or send `RegenerateLevel` to replace it with a new level.
+/// With `record_steps` the steps of the generator are replayed before the level is shown.
+pub struct LevelGeneratorPlugin;
+
+impl Plugin for LevelGeneratorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelGenConfig>()
+            .add_event::<LevelGenerated>()
+            .add_event::<LevelDecorated>()
+            .add_event::<RegenerateLevel>()
+            .add_startup_system(setup_level)
+            .add_system(regenerate_on_key)
+            .add_system(regenerate_level)
+            .add_system(visualizer::replay_controls)
+            .add_system_to_stage(CoreStage::PostUpdate, visualizer::replay_steps);
+    }
+}
+
+fn spawn_level(
+    grid: &LevelGrid,
+    objective: Option<&LevelObjective>,
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+    rng: &mut StdRng,
+) -> Entity {
+    let root = decorator::decorate_level(grid, commands, meshes, materials, asset_server, rng);
+    if let Some(objective) = objective {
+        decorator::add_objective_markers(grid, objective, commands, root, meshes, materials);
+    }
+
+    let (size, scale) = (grid.size(), grid.scale());
+    let floor_plane = meshes.add(Mesh::from(shape::Plane {
+        size: (size.0.max(size.1) + 3) as f32 * scale.0.max(scale.1),
+    }));
+    let ground = commands
+        .spawn_bundle(PbrBundle {
+            mesh: floor_plane,
+            transform: Transform::from_xyz(
+                (size.0 / 2) as f32 * scale.0,
+                -scale.2,
+                (size.1 / 2) as f32 * scale.1,
+            ),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgb(0., 0., 0.),
+                perceptual_roughness: 1.0,
+                ..default()
+            }),
+            ..default()
+        })
+        .insert(LevelPiece::Ground)
+        .id();
+    commands.entity(root).add_child(ground);
+
+    root
+}
+
+/// Pick the start and goal of a new level, and make them available as the `LevelObjective` resource
+fn update_objective(grid: &LevelGrid, commands: &mut Commands) -> Option<LevelObjective> {
+    let objective = grid.find_objective();
+    match &objective {
+        Some(objective) => {
+            println!(
+                "Start at {:?}, goal at {:?}, {} steps apart",
+                objective.start,
+                objective.goal,
+                objective.length()
+            );
+            commands.insert_resource(objective.clone());
+        }
+        None => {
+            println!("The level has no floor to put a start and goal on");
+            commands.remove_resource::<LevelObjective>();
+        }
+    }
+
+    objective
+}
+
+fn setup_level(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+    config: Res<LevelGenConfig>,
+    mut generated_events: EventWriter<LevelGenerated>,
+    mut decorated_events: EventWriter<LevelDecorated>,
+) {
+    let mut seed = LevelSeed::new(config.seed);
+
+    let (grid, steps) = match &config.load_path {
+        Some(path) => {
+            println!("Loading level from {}", path.display());
+            let grid = load_level(path)
+                .unwrap_or_else(|err| panic!("Could not load {}: {err}", path.display()));
+            let report = grid.validate();
+            if !report.is_valid() {
+                println!("{}: {report}", path.display());
+            }
+            (Box::new(grid), vec![])
+        }
+        None => {
+            println!("Generating level with seed {}", seed.seed);
+            let level = generator::generate_level(&config, &mut seed.rng);
+            (level.grid, level.steps)
+        }
+    };
+
+    if let Some(path) = &config.save_path {
+        save_level(&grid, path)
+            .unwrap_or_else(|err| panic!("Could not save {}: {err}", path.display()));
+        println!("Saved level to {}", path.display());
+    }
+
+    generated_events.send(LevelGenerated);
+
+    let objective = update_objective(&grid, &mut commands);
+    let root = spawn_level(
+        &grid,
+        objective.as_ref(),
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        asset_server,
+        &mut seed.rng,
+    );
+    if !steps.is_empty() {
+        visualizer::start_replay(steps, root, &mut commands, &mut meshes, &mut materials);
+    }
+
+    decorated_events.send(LevelDecorated {
+        start: objective.map(|objective| objective.start),
+    });
+
+    commands.insert_resource(CurrentLevel(grid));
+    commands.insert_resource(seed);
+}
+
+fn regenerate_on_key(keys: Res<Input<KeyCode>>, mut events: EventWriter<RegenerateLevel>) {
+    if keys.just_pressed(REGENERATE_KEY) {
+        events.send(RegenerateLevel { seed: None });
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn regenerate_level(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+    mut config: ResMut<LevelGenConfig>,
+    mut seed: ResMut<LevelSeed>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut regenerate_events: EventReader<RegenerateLevel>,
+    mut generated_events: EventWriter<LevelGenerated>,
+    mut decorated_events: EventWriter<LevelDecorated>,
+    level_roots: Query<Entity, With<LevelRoot>>,
+) {
+    // Several requests in the same frame only need one new level
+    let event = match regenerate_events.iter().last() {
+        Some(event) => event,
+        None => return,
+    };
+
+    config.seed = event.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    *seed = LevelSeed::new(config.seed);
+
+    println!("Regenerating level with seed {}", seed.seed);
+    let level = generator::generate_level(&config, &mut seed.rng);
+    current_level.0 = level.grid;
+    generated_events.send(LevelGenerated);
+
+    for root in level_roots.iter() {
+        commands.entity(root).despawn_recursive();
+    }
+
+    let objective = update_objective(&current_level.0, &mut commands);
+    let root = spawn_level(
+        &current_level.0,
+        objective.as_ref(),
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        asset_server,
+        &mut seed.rng,
+    );
+    if !level.steps.is_empty() {
+        visualizer::start_replay(
+            level.steps,
+            root,
+            &mut commands,
+            &mut meshes,
+            &mut materials,
+        );
+    }
+    decorated_events.send(LevelDecorated {
+        start: objective.map(|objective| objective.start),
+    });
+}
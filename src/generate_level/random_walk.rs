@@ -0,0 +1,332 @@
+use super::config::LevelGenConfig;
+use super::generator::{remove_dead_ends, GenerationStats, LevelGenerator};
+use super::grid::{GenerationStep, GridTile, LevelGrid, DIAGONAL_DIRECTIONS, DIRECTIONS};
+use super::height_map::generate_height_map;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::time::Instant;
+
+type CursorPosition = (isize, isize, isize);
+
+/// A tile that can be placed next to the cursor, where it is placed,
+/// and for stairs the floor tile the stair leads to. Ladders lead to the tile above or below them.
+type PathOption = (GridTile, (isize, isize), Option<(isize, isize)>);
+
+/// Every tile that can be placed next to the cursor,
+/// and the positions where a stair was left out because it would be diagonal from the same stair.
+/// Ladders are only an option with `ladders`, where there is no room for a stair.
+fn generate_options(
+    grid: &LevelGrid,
+    cursor_position: CursorPosition,
+    ladders: bool,
+) -> (Vec<PathOption>, Vec<CursorPosition>) {
+    let mut options = Vec::new();
+    let mut expanded_diagonals = vec![];
+
+    for cardinal_direction in [
+        (-1, 0, GridTile::StairsWestTop, GridTile::StairsEastBottom),
+        (0, -1, GridTile::StairsNorthTop, GridTile::StairsSouthBottom),
+        (1, 0, GridTile::StairsEastTop, GridTile::StairsWestBottom),
+        (0, 1, GridTile::StairsSouthTop, GridTile::StairsNorthBottom),
+    ] {
+        let offset_tile_position = (
+            cardinal_direction.0 + cursor_position.0,
+            cardinal_direction.1 + cursor_position.1,
+        );
+
+        if grid.get(
+            offset_tile_position.0,
+            offset_tile_position.1,
+            cursor_position.2,
+        ) == GridTile::Empty
+            && grid.is_valid_height(
+                offset_tile_position.0,
+                offset_tile_position.1,
+                cursor_position.2,
+            )
+        {
+            options.push((GridTile::Floor, offset_tile_position, None));
+
+            let expanded_grid_tile = (
+                (offset_tile_position.0 + cardinal_direction.0),
+                (offset_tile_position.1 + cardinal_direction.1),
+            );
+
+            if DIRECTIONS.iter().any(|dir| {
+                grid.get(
+                    offset_tile_position.0 + dir.0,
+                    offset_tile_position.1 + dir.1,
+                    cursor_position.2,
+                )
+                .is_stair_tile()
+            }) {
+                continue;
+            }
+
+            if grid.get(
+                offset_tile_position.0,
+                offset_tile_position.1,
+                cursor_position.2 - 1,
+            ) == GridTile::Empty
+                && grid.get(
+                    expanded_grid_tile.0,
+                    expanded_grid_tile.1,
+                    cursor_position.2 - 1,
+                ) == GridTile::Empty
+                && grid.is_valid_height(
+                    offset_tile_position.0,
+                    offset_tile_position.1,
+                    cursor_position.2 - 1,
+                )
+                && grid.is_valid_height(
+                    expanded_grid_tile.0,
+                    expanded_grid_tile.1,
+                    cursor_position.2 - 1,
+                )
+            {
+                // Prevent the same stair spawning diagonal from itself, which looks ugly
+                if !DIAGONAL_DIRECTIONS.iter().any(|(a, b)| {
+                    grid.get(
+                        offset_tile_position.0 + a,
+                        offset_tile_position.1 + b,
+                        cursor_position.2,
+                    ) == cardinal_direction.2
+                }) {
+                    options.push((
+                        cardinal_direction.2,
+                        offset_tile_position,
+                        Some(expanded_grid_tile),
+                    ))
+                } else {
+                    expanded_diagonals.push((
+                        offset_tile_position.0,
+                        offset_tile_position.1,
+                        cursor_position.2,
+                    ));
+                }
+            } else if ladders
+                && grid.get(
+                    offset_tile_position.0,
+                    offset_tile_position.1,
+                    cursor_position.2 - 1,
+                ) == GridTile::Empty
+                && grid.is_valid_height(
+                    offset_tile_position.0,
+                    offset_tile_position.1,
+                    cursor_position.2 - 1,
+                )
+            {
+                // There is no room for a stair, but a ladder only needs the tile below
+                options.push((GridTile::LadderTop, offset_tile_position, None));
+            }
+
+            if grid.get(
+                offset_tile_position.0,
+                offset_tile_position.1,
+                cursor_position.2 + 1,
+            ) == GridTile::Empty
+                && grid.get(
+                    expanded_grid_tile.0,
+                    expanded_grid_tile.1,
+                    cursor_position.2 + 1,
+                ) == GridTile::Empty
+                && grid.is_valid_height(
+                    offset_tile_position.0,
+                    offset_tile_position.1,
+                    cursor_position.2 + 1,
+                )
+                && grid.is_valid_height(
+                    expanded_grid_tile.0,
+                    expanded_grid_tile.1,
+                    cursor_position.2 + 1,
+                )
+            {
+                // Prevent the same stair spawning diagonal from itself, which looks ugly
+                if !DIAGONAL_DIRECTIONS.iter().any(|(a, b)| {
+                    grid.get(
+                        offset_tile_position.0 + a,
+                        offset_tile_position.1 + b,
+                        cursor_position.2,
+                    ) == cardinal_direction.3
+                }) {
+                    options.push((
+                        cardinal_direction.3,
+                        offset_tile_position,
+                        Some(expanded_grid_tile),
+                    ))
+                } else {
+                    expanded_diagonals.push((
+                        offset_tile_position.0,
+                        offset_tile_position.1,
+                        cursor_position.2,
+                    ));
+                }
+            } else if ladders
+                && grid.get(
+                    offset_tile_position.0,
+                    offset_tile_position.1,
+                    cursor_position.2 + 1,
+                ) == GridTile::Empty
+                && grid.is_valid_height(
+                    offset_tile_position.0,
+                    offset_tile_position.1,
+                    cursor_position.2 + 1,
+                )
+            {
+                // There is no room for a stair, but a ladder only needs the tile above
+                options.push((GridTile::LadderBottom, offset_tile_position, None));
+            }
+        }
+    }
+
+    if !expanded_diagonals.is_empty() {
+        println!(
+            "Removed expanded diagonals: {} at {cursor_position:?}",
+            expanded_diagonals.len()
+        );
+    }
+
+    return (options, expanded_diagonals);
+}
+
+/// Walk from `cursor_position` until there is nowhere left to go. Every option is equally
+/// likely, except ladders which are picked `ladder_weight` times as often.
+fn generate_single_path(
+    grid: &mut LevelGrid,
+    mut cursor_position: CursorPosition,
+    ladder_weight: f32,
+    rng: &mut StdRng,
+    stats: &mut GenerationStats,
+) {
+    loop {
+        let (options, expanded_diagonals) =
+            generate_options(grid, cursor_position, ladder_weight > 0.);
+        stats.expanded_diagonals += expanded_diagonals.len();
+        for position in expanded_diagonals {
+            grid.record(GenerationStep::StairRejected { position });
+        }
+
+        println!("> {:?}", cursor_position);
+        let choice = if ladder_weight > 0. {
+            options
+                .choose_weighted(rng, |(tile, _, _)| {
+                    if tile.is_ladder_tile() {
+                        ladder_weight
+                    } else {
+                        1.
+                    }
+                })
+                .ok()
+        } else {
+            options.choose(rng)
+        };
+        match choice {
+            Some((new_tile_type, new_tile_position, new_new_tile_position)) => {
+                // get_grid_at(&grid, new_tile_position.0, new_tile_position.1, cursor_position.2);
+                assert_eq!(
+                    grid.get(new_tile_position.0, new_tile_position.1, cursor_position.2),
+                    GridTile::Empty
+                );
+                grid.set(
+                    new_tile_position.0,
+                    new_tile_position.1,
+                    cursor_position.2,
+                    *new_tile_type,
+                );
+                cursor_position.0 = new_tile_position.0;
+                cursor_position.1 = new_tile_position.1;
+                if new_tile_type.is_stair_tile() {
+                    if new_tile_type.is_bottom_stair_tile() {
+                        cursor_position.2 += 1;
+                    } else if new_tile_type.is_top_stair_tile() {
+                        cursor_position.2 -= 1;
+                    }
+                    println!("# {:?}", cursor_position);
+                    grid.set(
+                        new_tile_position.0,
+                        new_tile_position.1,
+                        cursor_position.2,
+                        new_tile_type.get_opposite_stair_tile().unwrap(),
+                    );
+
+                    let new_new_tile_position = new_new_tile_position.unwrap();
+                    cursor_position.0 = new_new_tile_position.0;
+                    cursor_position.1 = new_new_tile_position.1;
+
+                    grid.set(
+                        cursor_position.0,
+                        cursor_position.1,
+                        cursor_position.2,
+                        GridTile::Floor,
+                    );
+                } else if new_tile_type.is_ladder_tile() {
+                    if *new_tile_type == GridTile::LadderBottom {
+                        cursor_position.2 += 1;
+                    } else {
+                        cursor_position.2 -= 1;
+                    }
+                    println!("# {:?}", cursor_position);
+                    grid.set(
+                        cursor_position.0,
+                        cursor_position.1,
+                        cursor_position.2,
+                        new_tile_type.get_opposite_vertical_tile().unwrap(),
+                    );
+                }
+            }
+            None => {
+                grid.record(GenerationStep::WalkStalled {
+                    position: cursor_position,
+                });
+                break;
+            }
+        }
+    }
+}
+
+/// The original algorithm: rectangular height limits, then random walks from the center
+/// of the level, and finally removing the dead ends
+pub struct RandomWalkGenerator;
+
+impl LevelGenerator for RandomWalkGenerator {
+    fn generate(
+        &self,
+        config: &LevelGenConfig,
+        rng: &mut StdRng,
+        stats: &mut GenerationStats,
+    ) -> LevelGrid {
+        let dimensions = config.dimensions;
+        let start_time = Instant::now();
+        let mut grid = LevelGrid::new(dimensions, generate_height_map(config, rng));
+        if config.record_steps {
+            grid.start_recording();
+        }
+        stats.times.height_map = start_time.elapsed();
+
+        let start_time = Instant::now();
+
+        let center = (dimensions.size.0 / 2, dimensions.size.1 / 2);
+        let cursor_position: CursorPosition = (
+            center.0 as isize,
+            center.1 as isize,
+            grid.max_height[center.0][center.1] as isize,
+        );
+        grid.set(
+            cursor_position.0,
+            cursor_position.1,
+            cursor_position.2,
+            GridTile::Floor,
+        );
+
+        for _ in 0..config.path_count {
+            generate_single_path(&mut grid, cursor_position, config.ladder_weight, rng, stats);
+        }
+        stats.times.layout = start_time.elapsed();
+
+        if config.remove_dead_ends {
+            remove_dead_ends(&mut grid, stats);
+        }
+
+        return grid;
+    }
+}
@@ -0,0 +1,75 @@
+use super::grid::{GridTile, LevelGrid, Room};
+use rand::prelude::*;
+use rand::rngs::StdRng;
+
+/// The smallest and largest number of tiles along each side of a room
+const ROOM_SIZE: (isize, isize) = (2, 4);
+
+/// How many random rectangles are tried for every room before giving up on it
+const PLACEMENT_TRIES: usize = 50;
+
+/// Whether `room` can be carved into the grid: every tile has to be empty or a floor,
+/// within the height limits, on the same side of the walls of a building,
+/// and outside the other rooms.
+/// At least one of the tiles has to be walkable already, to connect the room to the level.
+fn fits(grid: &LevelGrid, room: &Room) -> bool {
+    let indoor = grid.is_indoor(room.min.0, room.min.1);
+    let mut connected = false;
+
+    for (x, y, z) in room.tiles() {
+        let tile = grid.get(x, y, z);
+        if !(tile == GridTile::Empty || tile == GridTile::Floor)
+            || !grid.is_valid_height(x, y, z)
+            || grid.is_indoor(x, y) != indoor
+            || grid.room_at((x, y, z)).is_some()
+        {
+            return false;
+        }
+
+        connected |= tile == GridTile::Floor;
+    }
+
+    connected
+}
+
+/// Carve up to `count` rectangular rooms into the level, each on a random storey and overlapping
+/// the paths that are already there. Floors let you walk to every side, so a room is joined to
+/// every path that runs into it.
+/// Returns the number of rooms added.
+pub fn add_rooms(grid: &mut LevelGrid, count: usize, rng: &mut StdRng) -> usize {
+    let size = grid.size();
+    let mut added = 0;
+
+    for _ in 0..count {
+        let room = (0..PLACEMENT_TRIES)
+            .map(|_| {
+                let width = rng.gen_range(ROOM_SIZE.0..=ROOM_SIZE.1);
+                let depth = rng.gen_range(ROOM_SIZE.0..=ROOM_SIZE.1);
+                let min = (
+                    rng.gen_range(0..=(size.0 as isize - width).max(0)),
+                    rng.gen_range(0..=(size.1 as isize - depth).max(0)),
+                );
+
+                Room {
+                    min,
+                    max: (min.0 + width - 1, min.1 + depth - 1),
+                    z: rng.gen_range(0..size.2 as isize),
+                }
+            })
+            .find(|room| fits(grid, room));
+
+        if let Some(room) = room {
+            println!(
+                "Added a room from {:?} to {:?} on storey {}",
+                room.min, room.max, room.z
+            );
+            for (x, y, z) in room.tiles() {
+                grid.set(x, y, z, GridTile::Floor);
+            }
+            grid.add_room(room);
+            added += 1;
+        }
+    }
+
+    added
+}
@@ -0,0 +1,19 @@
+use rand::prelude::*;
+use rand::rngs::StdRng;
+
+/// The seed the level is generated from, together with the RNG derived from it.
+/// Every random decision made while generating or decorating a level goes
+/// through `rng`, so the same seed always produces the same level.
+pub struct LevelSeed {
+    pub seed: u64,
+    pub rng: StdRng,
+}
+
+impl LevelSeed {
+    pub fn new(seed: u64) -> LevelSeed {
+        LevelSeed {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
@@ -0,0 +1,317 @@
+use super::grid::{GridTile, HeightMap, LevelDimensions, LevelGrid, Room, DIRECTIONS};
+
+const STAIR_TILES: [GridTile; 8] = [
+    GridTile::StairsEastTop,
+    GridTile::StairsEastBottom,
+    GridTile::StairsNorthTop,
+    GridTile::StairsNorthBottom,
+    GridTile::StairsWestTop,
+    GridTile::StairsWestBottom,
+    GridTile::StairsSouthTop,
+    GridTile::StairsSouthBottom,
+];
+
+/// The horizontal axes of a level
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Axis {
+    X,
+    Y,
+}
+
+impl GridTile {
+    /// The tile after turning or flipping the level, where `direction` maps every old direction to
+    /// its new one. Stairs keep their half but face the new way, other tiles look the same
+    /// from every side.
+    pub fn transformed(self, direction: impl Fn((isize, isize)) -> (isize, isize)) -> GridTile {
+        if !self.is_stair_tile() {
+            return self;
+        }
+
+        let old_direction = *DIRECTIONS
+            .iter()
+            .find(|&&old_direction| self.can_access(old_direction))
+            .unwrap();
+        *STAIR_TILES
+            .iter()
+            .find(|tile| {
+                tile.is_top_stair_tile() == self.is_top_stair_tile()
+                    && tile.can_access(direction(old_direction))
+            })
+            .unwrap()
+    }
+}
+
+impl LevelGrid {
+    /// Copy the grid into a new one of `dimensions`, moving every tile, door and room
+    /// with `position` and turning stairs with `direction`.
+    /// Locks are left out, they belong to the start and goal of the old level, which are picked
+    /// again once the level is moved. Their doors stay as plain doors, `add_locks` can lock
+    /// the new level again.
+    fn remapped(
+        &self,
+        dimensions: LevelDimensions,
+        position: impl Fn((isize, isize, isize)) -> (isize, isize, isize),
+        direction: impl Fn((isize, isize)) -> (isize, isize) + Copy,
+    ) -> LevelGrid {
+        let size = self.size();
+        let mut max_height: HeightMap = vec![vec![0; dimensions.size.1]; dimensions.size.0];
+        for x in 0..size.0 as isize {
+            for y in 0..size.1 as isize {
+                let (new_x, new_y, _) = position((x, y, 0));
+                max_height[new_x as usize][new_y as usize] = self.get_max_height(x, y);
+            }
+        }
+
+        let mut grid = LevelGrid::new(dimensions, max_height);
+        for (x, y, z) in self {
+            let (new_x, new_y, new_z) = position((x, y, z));
+            grid.set(
+                new_x,
+                new_y,
+                new_z,
+                self.get(x, y, z).transformed(direction),
+            );
+        }
+
+        for &(p1, p2) in self.doors() {
+            grid.add_door(position(p1), position(p2));
+        }
+        for room in self.rooms() {
+            let corner = position((room.min.0, room.min.1, room.z));
+            let other_corner = position((room.max.0, room.max.1, room.z));
+            grid.add_room(Room {
+                min: (corner.0.min(other_corner.0), corner.1.min(other_corner.1)),
+                max: (corner.0.max(other_corner.0), corner.1.max(other_corner.1)),
+                z: corner.2,
+            });
+        }
+
+        grid
+    }
+
+    /// The level turned a quarter turn, so the tile at `(x, y)` ends up at
+    /// `(depth - 1 - y, x)`. The width and depth of the level are swapped.
+    pub fn rotated(&self) -> LevelGrid {
+        let size = self.size();
+        let scale = self.scale();
+        let dimensions = LevelDimensions {
+            size: (size.1, size.0, size.2),
+            scale: (scale.1, scale.0, scale.2),
+        };
+
+        self.remapped(
+            dimensions,
+            |(x, y, z)| (size.1 as isize - 1 - y, x, z),
+            |(dx, dy)| (-dy, dx),
+        )
+    }
+
+    /// The level flipped along `axis`, so for `Axis::X` the tile at `x` ends up at `width - 1 - x`
+    pub fn mirrored(&self, axis: Axis) -> LevelGrid {
+        let size = self.size();
+
+        match axis {
+            Axis::X => self.remapped(
+                self.dimensions(),
+                |(x, y, z)| (size.0 as isize - 1 - x, y, z),
+                |(dx, dy)| (-dx, dy),
+            ),
+            Axis::Y => self.remapped(
+                self.dimensions(),
+                |(x, y, z)| (x, size.1 as isize - 1 - y, z),
+                |(dx, dy)| (dx, -dy),
+            ),
+        }
+    }
+
+    /// A level with `other` placed right after this one along `axis`.
+    /// Both levels have to be the same size along the other axes. The levels are joined wherever
+    /// walkable tiles of both meet at the seam, and the scale of this level is kept.
+    /// Like the other transforms, the locks of both levels are left out.
+    /// Nothing is changed at the seam and the result is not validated, so parts that do not meet
+    /// there stay cut off from each other. Check the result with `validate` before using it.
+    pub fn stitched(&self, other: &LevelGrid, axis: Axis) -> LevelGrid {
+        let size = self.size();
+        let other_size = other.size();
+        assert_eq!(
+            size.2, other_size.2,
+            "Levels have a different number of storeys"
+        );
+
+        let (new_size, shift) = match axis {
+            Axis::X => {
+                assert_eq!(size.1, other_size.1, "Levels have a different depth");
+                (
+                    (size.0 + other_size.0, size.1, size.2),
+                    (size.0 as isize, 0),
+                )
+            }
+            Axis::Y => {
+                assert_eq!(size.0, other_size.0, "Levels have a different width");
+                (
+                    (size.0, size.1 + other_size.1, size.2),
+                    (0, size.1 as isize),
+                )
+            }
+        };
+        let dimensions = LevelDimensions {
+            size: new_size,
+            scale: self.scale(),
+        };
+
+        // Start with this level in the corner, then copy the other one in after it
+        let mut grid = self.remapped(dimensions, |position| position, |direction| direction);
+        let moved = |(x, y, z): (isize, isize, isize)| (x + shift.0, y + shift.1, z);
+
+        for x in 0..other_size.0 as isize {
+            for y in 0..other_size.1 as isize {
+                let (new_x, new_y, _) = moved((x, y, 0));
+                grid.max_height[new_x as usize][new_y as usize] = other.get_max_height(x, y);
+            }
+        }
+        for (x, y, z) in other {
+            let (new_x, new_y, new_z) = moved((x, y, z));
+            grid.set(new_x, new_y, new_z, other.get(x, y, z));
+        }
+
+        for &(p1, p2) in other.doors() {
+            grid.add_door(moved(p1), moved(p2));
+        }
+        for room in other.rooms() {
+            grid.add_room(Room {
+                min: (room.min.0 + shift.0, room.min.1 + shift.1),
+                max: (room.max.0 + shift.0, room.max.1 + shift.1),
+                z: room.z,
+            });
+        }
+
+        grid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate_level::ascii::parse_grid;
+
+    /// A stair up from the floor on the right to the floor on the left, with a door and a room
+    fn fixture() -> LevelGrid {
+        let mut grid = parse_grid(
+            "4x2x2
+            z=0
+            .e##  1111
+            ..#.  0000
+            z=1
+            #E..  1111
+            ....  0000",
+        )
+        .unwrap();
+        grid.add_door((2, 0, 0), (2, 1, 0));
+        grid.add_room(Room {
+            min: (2, 0),
+            max: (3, 0),
+            z: 0,
+        });
+
+        grid
+    }
+
+    #[test]
+    fn stairs_turn_with_the_level() {
+        let rotate = |(dx, dy): (isize, isize)| (-dy, dx);
+        let turns = [
+            (GridTile::StairsEastBottom, GridTile::StairsSouthBottom),
+            (GridTile::StairsSouthBottom, GridTile::StairsWestBottom),
+            (GridTile::StairsWestBottom, GridTile::StairsNorthBottom),
+            (GridTile::StairsNorthBottom, GridTile::StairsEastBottom),
+            (GridTile::StairsEastTop, GridTile::StairsSouthTop),
+            (GridTile::StairsSouthTop, GridTile::StairsWestTop),
+            (GridTile::StairsWestTop, GridTile::StairsNorthTop),
+            (GridTile::StairsNorthTop, GridTile::StairsEastTop),
+        ];
+        for (tile, turned) in turns {
+            assert_eq!(tile.transformed(rotate), turned);
+        }
+
+        let flip_x = |(dx, dy): (isize, isize)| (-dx, dy);
+        let flip_y = |(dx, dy): (isize, isize)| (dx, -dy);
+        for tile in STAIR_TILES {
+            let flipped_x = tile.transformed(flip_x);
+            let flipped_y = tile.transformed(flip_y);
+            let faces_x = tile.can_access((1, 0)) || tile.can_access((-1, 0));
+
+            assert_eq!(flipped_x == tile, !faces_x, "{tile:?}");
+            assert_eq!(flipped_y == tile, faces_x, "{tile:?}");
+            assert_eq!(flipped_x.is_top_stair_tile(), tile.is_top_stair_tile());
+            assert_eq!(flipped_x.transformed(flip_x), tile);
+        }
+
+        assert_eq!(GridTile::Floor.transformed(rotate), GridTile::Floor);
+        assert_eq!(GridTile::LadderTop.transformed(flip_x), GridTile::LadderTop);
+    }
+
+    #[test]
+    fn four_turns_give_the_same_level() {
+        let grid = fixture();
+        let rotated = grid.rotated();
+
+        assert_eq!(rotated.size(), (2, 4, 2));
+        assert_eq!(rotated.get(1, 1, 0), GridTile::StairsSouthBottom);
+        assert_eq!(rotated.get_max_height(0, 3), 0);
+        assert!(rotated.validate().is_valid());
+        assert_eq!(rotated.rotated().rotated().rotated(), grid);
+    }
+
+    #[test]
+    fn mirroring_twice_gives_the_same_level() {
+        let grid = fixture();
+
+        for axis in [Axis::X, Axis::Y] {
+            let mirrored = grid.mirrored(axis);
+            assert!(mirrored.validate().is_valid(), "{axis:?}");
+            assert_eq!(mirrored.mirrored(axis), grid, "{axis:?}");
+        }
+        assert_eq!(
+            grid.mirrored(Axis::X).get(2, 0, 0),
+            GridTile::StairsWestBottom
+        );
+        assert_eq!(grid.mirrored(Axis::X).doors(), &[((1, 0, 0), (1, 1, 0))]);
+    }
+
+    #[test]
+    fn locks_are_left_out() {
+        let mut grid = fixture();
+        grid.add_lock((2, 0, 0), (3, 0, 0), (2, 1, 0));
+
+        let rotated = grid.rotated();
+        assert!(rotated.locks().is_empty());
+        assert_eq!(rotated.doors().len(), 2);
+    }
+
+    #[test]
+    fn stitched_levels_meet_at_the_seam() {
+        let grid = fixture();
+        let stitched = grid.stitched(&grid.mirrored(Axis::X), Axis::X);
+
+        assert_eq!(stitched.size(), (8, 2, 2));
+        assert_eq!(stitched.get(6, 0, 0), GridTile::StairsWestBottom);
+        assert!(stitched.doors().contains(&((5, 0, 0), (5, 1, 0))));
+        assert_eq!(stitched.rooms()[1].min, (4, 0));
+        assert!(stitched.validate().is_valid());
+
+        let rotated = grid.rotated();
+        let stitched = rotated.stitched(&rotated.mirrored(Axis::Y), Axis::Y);
+        assert_eq!(stitched.size(), (2, 8, 2));
+        assert!(stitched.validate().is_valid());
+
+        // Nothing is carved at the seam, so floors that do not line up stay cut off
+        let stitched = rotated.stitched(&rotated.rotated().rotated(), Axis::Y);
+        assert!(!stitched.validate().is_valid());
+    }
+
+    #[test]
+    #[should_panic(expected = "different depth")]
+    fn stitching_needs_the_same_depth() {
+        fixture().stitched(&fixture().rotated(), Axis::X);
+    }
+}
@@ -0,0 +1,343 @@
+use super::grid::{DoorEdge, GridTile, LevelGrid};
+use std::collections::HashSet;
+use std::fmt;
+
+/// A broken invariant found by `LevelGrid::validate`
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Violation {
+    /// A group of walkable tiles that can not be reached from the largest group.
+    /// `tile` is one of the tiles in the group.
+    Disconnected {
+        tile: (isize, isize, isize),
+        size: usize,
+    },
+    /// A stair or ladder tile without its opposite half directly above or below it
+    UnmatchedStair {
+        tile: (isize, isize, isize),
+        stair: GridTile,
+        found: GridTile,
+    },
+    /// A stair or ladder tile on a storey its column is not allowed to use
+    StairOutsideHeightLimits {
+        tile: (isize, isize, isize),
+        stair: GridTile,
+        max_height: u8,
+    },
+    /// A door that is not in a doorway, between an indoor and an outdoor tile
+    /// that can be walked between
+    MisplacedDoor { edge: DoorEdge },
+    /// The goal can not be reached with the keys that can be found
+    UnsolvableLocks,
+    /// There is not a single tile to walk on
+    NoWalkableTiles,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Violation::Disconnected { tile, size } => {
+                write!(f, "{size} tiles around {tile:?} can not be reached")
+            }
+            Violation::UnmatchedStair { tile, stair, found } => {
+                write!(f, "{stair:?} at {tile:?} leads to {found:?}")
+            }
+            Violation::StairOutsideHeightLimits {
+                tile,
+                stair,
+                max_height,
+            } => write!(
+                f,
+                "{stair:?} at {tile:?} is outside the height limit of {max_height}"
+            ),
+            Violation::MisplacedDoor { edge } => {
+                write!(
+                    f,
+                    "door between {:?} and {:?} is not in a doorway",
+                    edge.0, edge.1
+                )
+            }
+            Violation::UnsolvableLocks => {
+                write!(
+                    f,
+                    "the goal can not be reached with the keys that can be found"
+                )
+            }
+            Violation::NoWalkableTiles => write!(f, "the level has no tiles to walk on"),
+        }
+    }
+}
+
+/// Everything wrong with a level, empty if the level is valid
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct ValidationReport {
+    pub violations: Vec<Violation>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_valid() {
+            return writeln!(f, "Level is valid");
+        }
+
+        writeln!(f, "Level has {} violations:", self.violations.len())?;
+        for violation in &self.violations {
+            writeln!(f, "  {violation}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl LevelGrid {
+    /// Check that there are walkable tiles and that every one of them can reach every other one,
+    /// that every stair and ladder has its other half and fits in the height limits,
+    /// that every door is in a doorway, and that the keys of the locks can be found in time.
+    pub fn validate(&self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+
+        self.validate_connectivity(&mut report);
+
+        for (x, y, z) in self {
+            let stair = self.get(x, y, z);
+            let other_half = if stair.goes_up() {
+                (x, y, z + 1)
+            } else if stair.goes_down() {
+                (x, y, z - 1)
+            } else {
+                continue;
+            };
+
+            let found = self.get(other_half.0, other_half.1, other_half.2);
+            if Some(found) != stair.get_opposite_vertical_tile() {
+                report.violations.push(Violation::UnmatchedStair {
+                    tile: (x, y, z),
+                    stair,
+                    found,
+                });
+            }
+
+            if !self.is_valid_height(x, y, z) {
+                report.violations.push(Violation::StairOutsideHeightLimits {
+                    tile: (x, y, z),
+                    stair,
+                    max_height: self.get_max_height(x, y),
+                });
+            }
+        }
+
+        for &(p1, p2) in self.doors() {
+            if !self.get(p1.0, p1.1, p1.2).is_walkable()
+                || !self.get(p2.0, p2.1, p2.2).is_walkable()
+                || !self.can_access(p1, p2)
+                || self.is_indoor(p1.0, p1.1) == self.is_indoor(p2.0, p2.1)
+            {
+                report
+                    .violations
+                    .push(Violation::MisplacedDoor { edge: (p1, p2) });
+            }
+        }
+
+        if !self.locks().is_empty()
+            && self
+                .find_objective()
+                .and_then(|objective| self.solve_locks(objective.start, objective.goal))
+                .is_none()
+        {
+            report.violations.push(Violation::UnsolvableLocks);
+        }
+
+        return report;
+    }
+
+    fn validate_connectivity(&self, report: &mut ValidationReport) {
+        let mut visited = HashSet::new();
+        let mut components = vec![];
+
+        for (x, y, z) in self {
+            if self.get(x, y, z).is_walkable() && !visited.contains(&(x, y, z)) {
+                let component = self.reachable((x, y, z));
+                components.push(((x, y, z), component.len()));
+                visited.extend(component);
+            }
+        }
+
+        if components.is_empty() {
+            report.violations.push(Violation::NoWalkableTiles);
+        }
+
+        // The largest group is taken to be the level, everything else is cut off from it
+        let largest = (0..components.len()).max_by_key(|&index| components[index].1);
+        for (index, &(tile, size)) in components.iter().enumerate() {
+            if Some(index) != largest {
+                report
+                    .violations
+                    .push(Violation::Disconnected { tile, size });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate_level::ascii::parse_grid;
+
+    fn violations(grid: &LevelGrid) -> Vec<Violation> {
+        grid.validate().violations
+    }
+
+    #[test]
+    fn valid_level() {
+        let grid = parse_grid(
+            "3x1x2
+            z=0
+            .e#  111
+            z=1
+            #E.  111",
+        )
+        .unwrap();
+
+        assert!(grid.validate().is_valid());
+    }
+
+    #[test]
+    fn no_walkable_tiles() {
+        let grid = parse_grid(
+            "2x1x1
+            z=0
+            ..  00",
+        )
+        .unwrap();
+
+        assert_eq!(violations(&grid), vec![Violation::NoWalkableTiles]);
+    }
+
+    #[test]
+    fn disconnected_island() {
+        let grid = parse_grid(
+            "4x1x1
+            z=0
+            ##.#  0000",
+        )
+        .unwrap();
+
+        assert_eq!(
+            violations(&grid),
+            vec![Violation::Disconnected {
+                tile: (3, 0, 0),
+                size: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn unmatched_stair() {
+        let grid = parse_grid(
+            "2x1x2
+            z=0
+            e#  11
+            z=1
+            ..  11",
+        )
+        .unwrap();
+
+        assert_eq!(
+            violations(&grid),
+            vec![Violation::UnmatchedStair {
+                tile: (0, 0, 0),
+                stair: GridTile::StairsEastBottom,
+                found: GridTile::Empty
+            }]
+        );
+    }
+
+    #[test]
+    fn stair_above_the_height_limit() {
+        let grid = parse_grid(
+            "2x1x2
+            z=0
+            e#  01
+            z=1
+            E.  01",
+        )
+        .unwrap();
+
+        assert_eq!(
+            violations(&grid),
+            vec![Violation::StairOutsideHeightLimits {
+                tile: (0, 0, 1),
+                stair: GridTile::StairsEastTop,
+                max_height: 0
+            }]
+        );
+    }
+
+    #[test]
+    fn door_leading_nowhere() {
+        let mut grid = parse_grid(
+            "2x1x1
+            z=0
+            #.  00",
+        )
+        .unwrap();
+        grid.add_door((0, 0, 0), (1, 0, 0));
+
+        assert_eq!(
+            violations(&grid),
+            vec![Violation::MisplacedDoor {
+                edge: ((0, 0, 0), (1, 0, 0))
+            }]
+        );
+    }
+
+    #[test]
+    fn door_in_the_open() {
+        let mut grid = parse_grid(
+            "2x1x3
+            z=0
+            ##  01
+            z=1
+            ..  01
+            z=2
+            ..  01",
+        )
+        .unwrap();
+        grid.add_door((0, 0, 0), (1, 0, 0));
+        assert!(grid.validate().is_valid());
+
+        // Both tiles outside, so there is no wall around the door
+        grid.max_height[1][0] = 0;
+        assert_eq!(
+            violations(&grid),
+            vec![Violation::MisplacedDoor {
+                edge: ((0, 0, 0), (1, 0, 0))
+            }]
+        );
+    }
+
+    #[test]
+    fn unsolvable_locks() {
+        // The middle of the corridor is inside, so both locks are in a doorway
+        let mut grid = parse_grid(
+            "5x1x3
+            z=0
+            #####  00100
+            z=1
+            .....  00100
+            z=2
+            .....  00100",
+        )
+        .unwrap();
+        // Each key is behind the other lock, so neither end of the corridor can get out
+        grid.add_lock((1, 0, 0), (2, 0, 0), (4, 0, 0));
+        grid.add_lock((2, 0, 0), (3, 0, 0), (0, 0, 0));
+
+        assert_eq!(violations(&grid), vec![Violation::UnsolvableLocks]);
+    }
+}
@@ -0,0 +1,209 @@
+use super::ascii::glyph_to_tile;
+use super::grid::{GridTile, LevelGrid};
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use serde::Deserialize;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+/// A tile of a template that has to be joined to the paths of the level.
+/// `direction` points out of the template, towards the tile it connects to.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Deserialize)]
+pub struct Connection {
+    pub position: (isize, isize, isize),
+    pub direction: (isize, isize),
+}
+
+/// The on-disk form of a `VignetteTemplate`. The storeys go from the bottom up, and every storey
+/// is a list of rows with the first row at `y = 0`, written with the glyphs of the ASCII level
+/// format:
+///
+/// ```text
+/// (
+///     name: "Balcony",
+///     storeys: [["###", "###"]],
+///     connections: [(position: (1, 1, 0), direction: (0, 1))],
+/// )
+/// ```
+#[derive(Deserialize)]
+struct TemplateFile {
+    name: String,
+    storeys: Vec<Vec<String>>,
+    connections: Vec<Connection>,
+}
+
+/// A small set piece authored by hand, which is placed into a level whole.
+/// Empty tiles of the template are left as they are in the level.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct VignetteTemplate {
+    pub name: String,
+    pub size: (usize, usize, usize),
+    /// Indexed as `[x][y][z]`
+    pub tiles: Vec<Vec<Vec<GridTile>>>,
+    pub connections: Vec<Connection>,
+}
+
+fn invalid_data(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+impl TryFrom<TemplateFile> for VignetteTemplate {
+    type Error = Error;
+
+    fn try_from(file: TemplateFile) -> Result<VignetteTemplate, Error> {
+        let depth = file.storeys.first().map_or(0, |rows| rows.len());
+        let width = file
+            .storeys
+            .first()
+            .and_then(|rows| rows.first())
+            .map_or(0, |row| row.chars().count());
+        if width == 0 || depth == 0 {
+            return Err(invalid_data("a template needs at least one tile"));
+        }
+
+        let size = (width, depth, file.storeys.len());
+        let mut tiles = vec![vec![vec![GridTile::Empty; size.2]; size.1]; size.0];
+        for (z, rows) in file.storeys.iter().enumerate() {
+            if rows.len() != depth || rows.iter().any(|row| row.chars().count() != width) {
+                return Err(invalid_data(
+                    "every storey needs the same number of rows and columns",
+                ));
+            }
+
+            for (y, row) in rows.iter().enumerate() {
+                for (x, glyph) in row.chars().enumerate() {
+                    tiles[x][y][z] = glyph_to_tile(glyph)
+                        .ok_or_else(|| invalid_data(&format!("unknown tile {glyph}")))?;
+                }
+            }
+        }
+
+        let template = VignetteTemplate {
+            name: file.name,
+            size,
+            tiles,
+            connections: file.connections,
+        };
+        if template.connections.is_empty()
+            || template
+                .connections
+                .iter()
+                .any(|connection| !template.get(connection.position).is_walkable())
+        {
+            return Err(invalid_data("connections have to be on walkable tiles"));
+        }
+
+        Ok(template)
+    }
+}
+
+impl VignetteTemplate {
+    pub fn load(path: impl AsRef<Path>) -> Result<VignetteTemplate, Error> {
+        let text = std::fs::read_to_string(path)?;
+        let file: TemplateFile =
+            ron::from_str(&text).map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+
+        VignetteTemplate::try_from(file)
+    }
+
+    /// Every `.ron` file in `directory`, in the order of their names
+    pub fn load_directory(directory: impl AsRef<Path>) -> Result<Vec<VignetteTemplate>, Error> {
+        let mut paths: Vec<_> = std::fs::read_dir(directory)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<_, _>>()?;
+        paths.retain(|path| path.extension().is_some_and(|extension| extension == "ron"));
+        paths.sort();
+
+        paths.iter().map(VignetteTemplate::load).collect()
+    }
+
+    /// The tile at a position inside the template, `Empty` outside of it
+    pub fn get(&self, (x, y, z): (isize, isize, isize)) -> GridTile {
+        if x < 0
+            || x >= self.size.0 as isize
+            || y < 0
+            || y >= self.size.1 as isize
+            || z < 0
+            || z >= self.size.2 as isize
+        {
+            return GridTile::Empty;
+        }
+        self.tiles[x as usize][y as usize][z as usize]
+    }
+
+    /// The tiles of the template that are not empty, relative to its corner
+    fn placed_tiles(&self) -> impl Iterator<Item = ((isize, isize, isize), GridTile)> + '_ {
+        (0..self.size.0 as isize)
+            .flat_map(move |x| (0..self.size.1 as isize).map(move |y| (x, y)))
+            .flat_map(move |(x, y)| (0..self.size.2 as isize).map(move |z| (x, y, z)))
+            .map(|position| (position, self.get(position)))
+            .filter(|(_, tile)| *tile != GridTile::Empty)
+    }
+
+    /// Whether the template fits with its lowest corner at `offset`: the whole box it covers has
+    /// to be empty, its tiles within the height limits, and at least one of its connections has to
+    /// lead to a walkable tile of the level that leads back into it.
+    /// No path is carved to reach a template, so it is only placed where one of its connections
+    /// already borders a path of the level.
+    fn fits(&self, grid: &LevelGrid, offset: (isize, isize, isize)) -> bool {
+        let is_empty = (0..self.size.0 as isize).all(|x| {
+            (0..self.size.1 as isize).all(|y| {
+                (0..self.size.2 as isize)
+                    .all(|z| grid.get(offset.0 + x, offset.1 + y, offset.2 + z) == GridTile::Empty)
+            })
+        });
+        if !is_empty {
+            return false;
+        }
+
+        let within_limits = self
+            .placed_tiles()
+            .all(|((x, y, z), _)| grid.is_valid_height(offset.0 + x, offset.1 + y, offset.2 + z));
+
+        within_limits
+            && self.connections.iter().any(|connection| {
+                let (x, y, z) = connection.position;
+                let (dx, dy) = connection.direction;
+                let outside = grid.get(offset.0 + x + dx, offset.1 + y + dy, offset.2 + z);
+                self.get(connection.position).can_access((dx, dy))
+                    && outside.is_walkable()
+                    && outside.can_access((-dx, -dy))
+            })
+    }
+}
+
+/// Place up to `count` templates, each a random one of `templates`, where they fit in the level.
+/// See `VignetteTemplate::fits` for where that is, templates are only joined to paths that are
+/// already there. Returns the number of templates placed.
+pub fn add_vignettes(
+    grid: &mut LevelGrid,
+    templates: &[VignetteTemplate],
+    count: usize,
+    rng: &mut StdRng,
+) -> usize {
+    let size = grid.size();
+    let mut added = 0;
+
+    for _ in 0..count {
+        let template = match templates.choose(rng) {
+            Some(template) => template,
+            None => return added,
+        };
+
+        let offsets: Vec<_> = (0..size.0 as isize)
+            .flat_map(|x| (0..size.1 as isize).map(move |y| (x, y)))
+            .flat_map(|(x, y)| (0..size.2 as isize).map(move |z| (x, y, z)))
+            .filter(|&offset| template.fits(grid, offset))
+            .collect();
+
+        if let Some(&offset) = offsets.choose(rng) {
+            println!("Placed {} at {offset:?}", template.name);
+            for ((x, y, z), tile) in template.placed_tiles() {
+                grid.set(offset.0 + x, offset.1 + y, offset.2 + z, tile);
+            }
+            added += 1;
+        }
+    }
+
+    added
+}
@@ -0,0 +1,200 @@
+use super::decorator::LevelRoot;
+use super::grid::{GenerationStep, GridTile};
+use super::plugin::CurrentLevel;
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+const PAUSE_KEY: KeyCode = KeyCode::P;
+const STEP_KEY: KeyCode = KeyCode::N;
+const FASTER_KEY: KeyCode = KeyCode::Equals;
+const SLOWER_KEY: KeyCode = KeyCode::Minus;
+const SKIP_KEY: KeyCode = KeyCode::Return;
+
+const STARTING_SPEED: f32 = 20.0;
+
+/// Meshes and materials of the placeholder cubes
+struct ReplayAssets {
+    cube: Handle<Mesh>,
+    marker: Handle<Mesh>,
+    floor: Handle<StandardMaterial>,
+    stair_bottom: Handle<StandardMaterial>,
+    stair_top: Handle<StandardMaterial>,
+    ladder: Handle<StandardMaterial>,
+    walk_stalled: Handle<StandardMaterial>,
+    stair_rejected: Handle<StandardMaterial>,
+}
+
+/// Replays the steps the generator took with placeholder cubes, while the decorated level is hidden.
+/// P pauses, N takes a single step while paused, + and - change the speed and Enter skips to the end.
+pub struct GenerationReplay {
+    steps: Vec<GenerationStep>,
+    next_step: usize,
+    paused: bool,
+    steps_per_second: f32,
+    /// Time towards the next step, in steps
+    progress: f32,
+    /// The decorated level, shown again once the replay is done
+    level_root: Entity,
+    /// The parent of every cube
+    replay_root: Entity,
+    cubes: HashMap<(isize, isize, isize), Entity>,
+    assets: ReplayAssets,
+}
+
+/// Hide the level with root `level_root` and start replaying `steps` in its place
+pub(super) fn start_replay(
+    steps: Vec<GenerationStep>,
+    level_root: Entity,
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+) {
+    println!(
+        "Replaying {} generation steps, P to pause, N to step, +/- to change the speed, Enter to skip",
+        steps.len()
+    );
+
+    commands
+        .entity(level_root)
+        .insert(Visibility { is_visible: false });
+
+    // Tagged as part of the level, so regenerating the level also removes the replay
+    let replay_root = commands
+        .spawn_bundle(SpatialBundle::default())
+        .insert(LevelRoot)
+        .id();
+
+    commands.insert_resource(GenerationReplay {
+        steps,
+        next_step: 0,
+        paused: false,
+        steps_per_second: STARTING_SPEED,
+        progress: 0.,
+        level_root,
+        replay_root,
+        cubes: HashMap::new(),
+        assets: ReplayAssets {
+            cube: meshes.add(Mesh::from(shape::Cube { size: 0.5 })),
+            marker: meshes.add(Mesh::from(shape::Icosphere {
+                radius: 0.3,
+                subdivisions: 2,
+            })),
+            floor: materials.add(Color::rgb(0.6, 0.6, 0.6).into()),
+            stair_bottom: materials.add(Color::rgb(0.2, 0.4, 0.9).into()),
+            stair_top: materials.add(Color::rgb(0.4, 0.8, 1.0).into()),
+            ladder: materials.add(Color::rgb(0.6, 0.4, 0.2).into()),
+            walk_stalled: materials.add(Color::rgb(0.9, 0.1, 0.1).into()),
+            stair_rejected: materials.add(Color::rgb(1.0, 0.6, 0.0).into()),
+        },
+    });
+}
+
+pub(super) fn replay_controls(keys: Res<Input<KeyCode>>, replay: Option<ResMut<GenerationReplay>>) {
+    let mut replay = match replay {
+        Some(replay) => replay,
+        None => return,
+    };
+
+    if keys.just_pressed(PAUSE_KEY) {
+        replay.paused = !replay.paused;
+    }
+    if keys.just_pressed(STEP_KEY) && replay.paused {
+        replay.progress += 1.;
+    }
+    if keys.just_pressed(FASTER_KEY) {
+        replay.steps_per_second *= 2.;
+    }
+    if keys.just_pressed(SLOWER_KEY) {
+        replay.steps_per_second = (replay.steps_per_second / 2.).max(1.);
+    }
+    if keys.just_pressed(SKIP_KEY) {
+        replay.next_step = replay.steps.len();
+    }
+}
+
+impl GenerationReplay {
+    /// Show a single step, replacing the cube that was at the same position
+    fn apply_step(&mut self, step: GenerationStep, level: &CurrentLevel, commands: &mut Commands) {
+        let (position, mesh, material) = match step {
+            GenerationStep::Set { position, tile } => {
+                let material = match tile {
+                    GridTile::Empty | GridTile::OutOfBounds => None,
+                    tile if tile.is_bottom_stair_tile() => Some(&self.assets.stair_bottom),
+                    tile if tile.is_top_stair_tile() => Some(&self.assets.stair_top),
+                    tile if tile.is_ladder_tile() => Some(&self.assets.ladder),
+                    _ => Some(&self.assets.floor),
+                };
+                (position, &self.assets.cube, material)
+            }
+            GenerationStep::WalkStalled { position } => {
+                println!("Walk stalled at {position:?}");
+                (
+                    position,
+                    &self.assets.marker,
+                    Some(&self.assets.walk_stalled),
+                )
+            }
+            GenerationStep::StairRejected { position } => {
+                println!("Stair rejected at {position:?}");
+                (
+                    position,
+                    &self.assets.marker,
+                    Some(&self.assets.stair_rejected),
+                )
+            }
+        };
+
+        if let Some(cube) = self.cubes.remove(&position) {
+            commands.entity(cube).despawn_recursive();
+        }
+
+        if let Some(material) = material {
+            let cube = commands
+                .spawn_bundle(PbrBundle {
+                    mesh: mesh.clone(),
+                    material: material.clone(),
+                    transform: Transform::from_translation(
+                        level.0.tile_position(position) + Vec3::Y * 0.25,
+                    ),
+                    ..default()
+                })
+                .id();
+            commands.entity(self.replay_root).add_child(cube);
+            self.cubes.insert(position, cube);
+        }
+    }
+}
+
+/// Runs after the level is regenerated, so it never sees a replay of a level that was just removed
+pub(super) fn replay_steps(
+    mut commands: Commands,
+    time: Res<Time>,
+    level: Option<Res<CurrentLevel>>,
+    replay: Option<ResMut<GenerationReplay>>,
+    mut visibilities: Query<&mut Visibility>,
+) {
+    let (mut replay, level) = match (replay, level) {
+        (Some(replay), Some(level)) => (replay, level),
+        _ => return,
+    };
+
+    if !replay.paused {
+        replay.progress += time.delta_seconds() * replay.steps_per_second;
+    }
+
+    while replay.progress >= 1. && replay.next_step < replay.steps.len() {
+        replay.progress -= 1.;
+        let step = replay.steps[replay.next_step];
+        replay.next_step += 1;
+        replay.apply_step(step, &level, &mut commands);
+    }
+
+    if replay.next_step >= replay.steps.len() {
+        println!("Replay done");
+        commands.entity(replay.replay_root).despawn_recursive();
+        if let Ok(mut visibility) = visibilities.get_mut(replay.level_root) {
+            visibility.is_visible = true;
+        }
+        commands.remove_resource::<GenerationReplay>();
+    }
+}
@@ -0,0 +1,315 @@
+use super::config::LevelGenConfig;
+use super::generator::{remove_dead_ends, GenerationStats, LevelGenerator};
+use super::grid::{GridTile, LevelGrid};
+use super::height_map::generate_height_map;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use std::collections::HashSet;
+use std::time::Instant;
+
+/// Every tile that can be placed, a set of them is stored as a bit mask of indices in this list
+const TILES: [GridTile; 12] = [
+    GridTile::Empty,
+    GridTile::Floor,
+    GridTile::StairsEastTop,
+    GridTile::StairsEastBottom,
+    GridTile::StairsNorthTop,
+    GridTile::StairsNorthBottom,
+    GridTile::StairsWestTop,
+    GridTile::StairsWestBottom,
+    GridTile::StairsSouthTop,
+    GridTile::StairsSouthBottom,
+    GridTile::LadderTop,
+    GridTile::LadderBottom,
+];
+
+/// The four horizontal directions followed by up and down
+const NEIGHBOUR_DIRECTIONS: [(isize, isize, isize); 6] = [
+    (1, 0, 0),
+    (-1, 0, 0),
+    (0, -1, 0),
+    (0, 1, 0),
+    (0, 0, 1),
+    (0, 0, -1),
+];
+
+/// How many times the tiles are collapsed from scratch when they end up contradicting each other
+const MAX_CONTRADICTIONS: usize = 20;
+
+type TileSet = u16;
+
+const ALL_TILES: TileSet = (1 << TILES.len()) - 1;
+const EMPTY_ONLY: TileSet = 1;
+
+/// Whether `a` can have `b` as its neighbour in `direction`.
+/// These are the rules the random walk follows when placing tiles:
+/// stairs have to lead to a tile that lets you walk back onto them,
+/// the halves of a stair or ladder have to be above each other, and two stairs are never side by side.
+fn is_compatible(a: GridTile, b: GridTile, direction: (isize, isize, isize)) -> bool {
+    match direction {
+        (0, 0, 1) => (a.goes_up() || b.goes_down()) == (a.get_opposite_vertical_tile() == Some(b)),
+        (0, 0, -1) => is_compatible(b, a, (0, 0, 1)),
+        (dx, dy, 0) => {
+            if a.is_stair_tile() && b.is_stair_tile() {
+                return false;
+            }
+
+            let leads_to = |from: GridTile, to: GridTile, direction: (isize, isize)| {
+                !from.is_stair_tile()
+                    || !from.can_access(direction)
+                    || to.can_access((-direction.0, -direction.1))
+            };
+
+            leads_to(a, b, (dx, dy)) && leads_to(b, a, (-dx, -dy))
+        }
+        _ => false,
+    }
+}
+
+/// For every tile and every direction in `NEIGHBOUR_DIRECTIONS`, the tiles that can be next to it
+struct AdjacencyTable([[TileSet; 6]; TILES.len()]);
+
+impl AdjacencyTable {
+    fn new() -> AdjacencyTable {
+        let mut table = [[0; 6]; TILES.len()];
+
+        for (a, row) in TILES.iter().zip(table.iter_mut()) {
+            for (direction, allowed) in NEIGHBOUR_DIRECTIONS.iter().zip(row.iter_mut()) {
+                for (index, b) in TILES.iter().enumerate() {
+                    if is_compatible(*a, *b, *direction) {
+                        *allowed |= 1 << index;
+                    }
+                }
+            }
+        }
+
+        AdjacencyTable(table)
+    }
+
+    /// Every tile that can be next to at least one of `tiles` in a direction
+    fn allowed_neighbours(&self, tiles: TileSet, direction: usize) -> TileSet {
+        (0..TILES.len())
+            .filter(|index| tiles & (1 << index) != 0)
+            .fold(0, |allowed, index| allowed | self.0[index][direction])
+    }
+}
+
+/// Fills the level by repeatedly picking the cell with the fewest possible tiles left,
+/// choosing one of them at random and removing every tile the neighbours can no longer be.
+/// Only the largest connected part of the result is kept.
+pub struct WaveFunctionCollapseGenerator {
+    /// How likely each tile is to be picked, relative to the others.
+    /// Tiles that are missing are never picked.
+    pub weights: Vec<(GridTile, f32)>,
+}
+
+/// Mostly floor with some empty space, stairs now and then and ladders rarely
+pub(super) fn default_weights() -> Vec<(GridTile, f32)> {
+    vec![
+        (GridTile::Empty, 3.0),
+        (GridTile::Floor, 7.0),
+        (GridTile::StairsEastTop, 0.5),
+        (GridTile::StairsEastBottom, 0.5),
+        (GridTile::StairsNorthTop, 0.5),
+        (GridTile::StairsNorthBottom, 0.5),
+        (GridTile::StairsWestTop, 0.5),
+        (GridTile::StairsWestBottom, 0.5),
+        (GridTile::StairsSouthTop, 0.5),
+        (GridTile::StairsSouthBottom, 0.5),
+        (GridTile::LadderTop, 0.2),
+        (GridTile::LadderBottom, 0.2),
+    ]
+}
+
+impl Default for WaveFunctionCollapseGenerator {
+    fn default() -> Self {
+        WaveFunctionCollapseGenerator {
+            weights: default_weights(),
+        }
+    }
+}
+
+impl WaveFunctionCollapseGenerator {
+    fn weight(&self, tile: GridTile) -> f32 {
+        self.weights
+            .iter()
+            .find(|(weighted_tile, _)| *weighted_tile == tile)
+            .map_or(0.0, |(_, weight)| *weight)
+    }
+
+    /// The tiles each cell can start out as, indexed as `[x][y][z]`.
+    /// Only empty tiles are allowed outside the height limits,
+    /// and no tile can lead out of the level.
+    fn initial_tiles(&self, grid: &LevelGrid, table: &AdjacencyTable) -> Vec<Vec<Vec<TileSet>>> {
+        let size = grid.size();
+        let weighted: TileSet = TILES
+            .iter()
+            .enumerate()
+            .filter(|(_, tile)| self.weight(**tile) > 0.0)
+            .fold(EMPTY_ONLY, |tiles, (index, _)| tiles | 1 << index);
+
+        let mut cells = vec![vec![vec![ALL_TILES; size.2]; size.1]; size.0];
+        for (x, y, z) in grid {
+            let mut tiles = if grid.is_valid_height(x, y, z) {
+                weighted
+            } else {
+                EMPTY_ONLY
+            };
+
+            for (direction, offset) in NEIGHBOUR_DIRECTIONS.iter().enumerate() {
+                if grid.get(x + offset.0, y + offset.1, z + offset.2) == GridTile::OutOfBounds {
+                    // Only tiles that are fine with an empty neighbour can be on the edge
+                    let opposite = direction ^ 1;
+                    tiles &= table.0[0][opposite];
+                }
+            }
+
+            cells[x as usize][y as usize][z as usize] = tiles;
+        }
+
+        cells
+    }
+
+    /// Collapse every cell, returning `None` if the cells end up contradicting each other
+    fn collapse(
+        &self,
+        grid: &LevelGrid,
+        table: &AdjacencyTable,
+        rng: &mut StdRng,
+    ) -> Option<Vec<Vec<Vec<TileSet>>>> {
+        let mut cells = self.initial_tiles(grid, table);
+        let positions: Vec<_> = grid.into_iter().collect();
+
+        for &(x, y, z) in &positions {
+            propagate(grid, table, &mut cells, (x, y, z))?;
+        }
+
+        loop {
+            // The cell with the fewest options left, ties are broken at random
+            let next = positions
+                .iter()
+                .map(|&(x, y, z)| ((x, y, z), cells[x as usize][y as usize][z as usize]))
+                .filter(|(_, tiles)| tiles.count_ones() > 1)
+                .map(|(position, tiles)| ((tiles.count_ones(), rng.gen::<u32>()), position))
+                .min();
+
+            let (x, y, z) = match next {
+                Some((_, position)) => position,
+                None => return Some(cells),
+            };
+
+            let tiles = cells[x as usize][y as usize][z as usize];
+            let options: Vec<usize> = (0..TILES.len())
+                .filter(|index| tiles & (1 << index) != 0)
+                .collect();
+            let index = *options
+                .choose_weighted(rng, |index| self.weight(TILES[*index]).max(f32::EPSILON))
+                .ok()?;
+
+            cells[x as usize][y as usize][z as usize] = 1 << index;
+            propagate(grid, table, &mut cells, (x, y, z))?;
+        }
+    }
+}
+
+/// Remove every tile from the neighbours of `start` that no longer fits next to it,
+/// and keep going with the neighbours of those cells
+fn propagate(
+    grid: &LevelGrid,
+    table: &AdjacencyTable,
+    cells: &mut [Vec<Vec<TileSet>>],
+    start: (isize, isize, isize),
+) -> Option<()> {
+    let mut stack = vec![start];
+
+    while let Some((x, y, z)) = stack.pop() {
+        let tiles = cells[x as usize][y as usize][z as usize];
+
+        for (direction, offset) in NEIGHBOUR_DIRECTIONS.iter().enumerate() {
+            let neighbour = (x + offset.0, y + offset.1, z + offset.2);
+            if grid.get(neighbour.0, neighbour.1, neighbour.2) == GridTile::OutOfBounds {
+                continue;
+            }
+
+            let cell = &mut cells[neighbour.0 as usize][neighbour.1 as usize][neighbour.2 as usize];
+            let allowed = *cell & table.allowed_neighbours(tiles, direction);
+            if allowed == 0 {
+                return None;
+            }
+            if allowed != *cell {
+                *cell = allowed;
+                stack.push(neighbour);
+            }
+        }
+    }
+
+    Some(())
+}
+
+/// Empty every walkable tile that is not part of the largest connected group
+fn keep_largest_component(grid: &mut LevelGrid) {
+    let mut visited = HashSet::new();
+    let mut largest = HashSet::new();
+
+    for (x, y, z) in &*grid {
+        if grid.get(x, y, z).is_walkable() && !visited.contains(&(x, y, z)) {
+            let component = grid.reachable((x, y, z));
+            visited.extend(component.iter().copied());
+            if component.len() > largest.len() {
+                largest = component;
+            }
+        }
+    }
+
+    let positions: Vec<_> = grid.into_iter().collect();
+    for (x, y, z) in positions {
+        if !largest.contains(&(x, y, z)) {
+            grid.set(x, y, z, GridTile::Empty);
+        }
+    }
+}
+
+impl LevelGenerator for WaveFunctionCollapseGenerator {
+    fn generate(
+        &self,
+        config: &LevelGenConfig,
+        rng: &mut StdRng,
+        stats: &mut GenerationStats,
+    ) -> LevelGrid {
+        let dimensions = config.dimensions;
+        let start_time = Instant::now();
+        let mut grid = LevelGrid::new(dimensions, generate_height_map(config, rng));
+        if config.record_steps {
+            grid.start_recording();
+        }
+        stats.times.height_map = start_time.elapsed();
+
+        let start_time = Instant::now();
+        let table = AdjacencyTable::new();
+
+        let cells = match (0..MAX_CONTRADICTIONS).find_map(|_| self.collapse(&grid, &table, rng)) {
+            Some(cells) => cells,
+            None => {
+                // The empty grid fails validation, so the level is generated again
+                println!("Wave function collapse kept running into contradictions");
+                stats.times.layout = start_time.elapsed();
+                return grid;
+            }
+        };
+
+        let positions: Vec<_> = grid.into_iter().collect();
+        for (x, y, z) in positions {
+            let tiles = cells[x as usize][y as usize][z as usize];
+            grid.set(x, y, z, TILES[tiles.trailing_zeros() as usize]);
+        }
+
+        keep_largest_component(&mut grid);
+        stats.times.layout = start_time.elapsed();
+
+        if config.remove_dead_ends {
+            remove_dead_ends(&mut grid, stats);
+        }
+
+        return grid;
+    }
+}
@@ -0,0 +1,19 @@
+#![allow(
+    unused_mut,
+    clippy::needless_return,
+    clippy::redundant_field_names,
+    clippy::match_like_matches_macro,
+    clippy::single_match,
+    clippy::collapsible_match
+)]
+
+#[cfg(feature = "bevy")]
+pub mod cubemap;
+pub mod generate_level;
+#[cfg(feature = "bevy")]
+pub mod normal_mapped_texture_loader;
+#[cfg(feature = "bevy")]
+pub mod physics;
+#[cfg(feature = "bevy")]
+pub mod rotate_camera;
+pub mod util;
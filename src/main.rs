@@ -1,15 +1,24 @@
+#![allow(
+    clippy::needless_return,
+    clippy::needless_update,
+    clippy::redundant_field_names,
+    clippy::match_like_matches_macro
+)]
+
 use bevy::prelude::*;
-use bevy_rapier3d::prelude::*;
-mod cubemap;
-mod generate_level;
-mod normal_mapped_texture_loader;
-mod physics;
-mod rotate_camera;
-mod util;
 use bevy::render::render_resource::{AddressMode, SamplerDescriptor};
 use bevy::render::texture::ImageSettings;
+use bevy_rapier3d::prelude::*;
+use level_generator::{
+    cubemap, generate_level, normal_mapped_texture_loader, physics, rotate_camera,
+};
 
 fn main() {
+    let config = generate_level::LevelGenConfig::from_args().unwrap_or_else(|err| {
+        eprintln!("{err}");
+        std::process::exit(2);
+    });
+
     App::new()
         .insert_resource(ImageSettings {
             default_sampler: SamplerDescriptor {
@@ -31,7 +40,9 @@ fn main() {
         .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
         .add_plugin(rotate_camera::PlayerPlugin)
         .add_plugin(cubemap::CubemapPlugin)
-        .add_startup_system(setup_level)
+        .insert_resource(config)
+        .add_plugin(generate_level::LevelGeneratorPlugin)
+        .add_startup_system(setup_lighting)
         .add_system(rotate_camera_system)
         .add_startup_system(enable_hot_reloading)
         // .add_system(fix_normal_mapped_textures::set_image_color_mode)
@@ -41,14 +52,7 @@ fn main() {
 #[derive(Component)]
 struct RotateCamera(f32);
 
-fn setup_level(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    asset_server: Res<AssetServer>,
-) {
-    // level
-    generate_level::generate_level(&mut commands, &mut meshes, &mut materials, asset_server);
+fn setup_lighting(mut commands: Commands) {
     // light
     // commands.spawn_bundle(PointLightBundle {
     //     point_light: PointLight {
@@ -1,16 +1,38 @@
+use crate::generate_level::{CurrentLevel, Door, DoorKey, LevelDecorated};
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
+use std::collections::HashSet;
 
 const PLAYER_HEIGHT: f32 = 1.0; // 1 m 75 cm
+/// Gravity is turned off while climbing, and back to this afterwards
+pub const PLAYER_GRAVITY_SCALE: f32 = 2.0;
+
+const INTERACT_KEY: KeyCode = KeyCode::E;
+/// How close the player has to be to the middle of a doorway to open or close its door
+const DOOR_REACH: f32 = 2.5;
+/// How close the player has to get to a key to pick it up
+const KEY_REACH: f32 = 1.5;
 
 #[derive(Component)]
 pub struct PlayerController;
 
+/// The rigid body of the player
+#[derive(Component)]
+pub struct PlayerBody;
+
+/// The locks the player has picked up the key of in the current level
+#[derive(Default)]
+pub struct HeldKeys(pub HashSet<usize>);
+
 pub struct PlayerControllerPlugin;
 
 impl Plugin for PlayerControllerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(spawn_player)
+        app.init_resource::<HeldKeys>()
+            .add_startup_system(spawn_player)
+            .add_system(move_player_to_spawn)
+            .add_system(pick_up_keys)
+            .add_system(toggle_doors)
             .add_system(print_player_position);
     }
 }
@@ -24,6 +46,7 @@ fn spawn_player(
     let material = materials.add(Color::rgb(0.9, 0.3, 0.9).into());
     commands
         .spawn()
+        .insert(PlayerBody)
         .insert(RigidBody::Dynamic)
         .insert_bundle(TransformBundle::from(
             Transform::from_xyz(6.0 * 3.0, 12.0, 6.0 * 3.0), //.looking_at(Vec3::new(0., 0., 0.), Vec3::Y),
@@ -51,7 +74,7 @@ fn spawn_player(
             linear_damping: 8.0,
             ..default()
         })
-        .insert(GravityScale(2.0))
+        .insert(GravityScale(PLAYER_GRAVITY_SCALE))
         // .insert(Sleeping::disabled())
         .with_children(|parent| {
             // let mut parent = commands;
@@ -83,6 +106,90 @@ fn spawn_player(
         });
 }
 
+/// Put the player back on solid ground whenever a new level is built, without any keys
+fn move_player_to_spawn(
+    mut events: EventReader<LevelDecorated>,
+    level: Option<Res<CurrentLevel>>,
+    mut held_keys: ResMut<HeldKeys>,
+    mut players: Query<(&mut Transform, &mut Velocity), With<PlayerBody>>,
+) {
+    let event = match events.iter().last() {
+        Some(event) => event,
+        None => return,
+    };
+    held_keys.0.clear();
+
+    let grid = match &level {
+        Some(level) => &level.0,
+        None => return,
+    };
+
+    let spawn_tile = match event.start {
+        Some(spawn_tile) => spawn_tile,
+        None => {
+            warn!("The level has no floor to spawn the player on");
+            return;
+        }
+    };
+
+    for (mut transform, mut velocity) in players.iter_mut() {
+        transform.translation = grid.tile_position(spawn_tile) + Vec3::Y * PLAYER_HEIGHT * 1.5;
+        velocity.linvel = Vec3::ZERO;
+    }
+}
+
+/// Pick up every key the player walks into
+fn pick_up_keys(
+    mut commands: Commands,
+    mut held_keys: ResMut<HeldKeys>,
+    players: Query<&Transform, With<PlayerBody>>,
+    door_keys: Query<(Entity, &DoorKey, &GlobalTransform)>,
+) {
+    for player in players.iter() {
+        for (entity, door_key, transform) in door_keys.iter() {
+            if transform.translation().distance(player.translation) < KEY_REACH {
+                println!("Picked up the key of lock {}", door_key.lock);
+                held_keys.0.insert(door_key.lock);
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
+
+/// Open or close the closest door within reach when the interact key is pressed.
+/// Locked doors are unlocked first, if the player has their key.
+fn toggle_doors(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    held_keys: Res<HeldKeys>,
+    players: Query<&Transform, With<PlayerBody>>,
+    mut doors: Query<(&mut Door, &mut Transform), Without<PlayerBody>>,
+) {
+    if !keys.just_pressed(INTERACT_KEY) {
+        return;
+    }
+
+    for player in players.iter() {
+        let closest = doors
+            .iter_mut()
+            .map(|(door, hinge)| (door.center.distance(player.translation), door, hinge))
+            .filter(|(distance, _, _)| *distance < DOOR_REACH)
+            .min_by(|a, b| a.0.total_cmp(&b.0));
+
+        if let Some((_, mut door, mut hinge)) = closest {
+            if let Some(lock) = door.lock {
+                if !held_keys.0.contains(&lock) {
+                    println!("This door is locked, find its key first");
+                    continue;
+                }
+                println!("Unlocked lock {lock}");
+                door.lock = None;
+            }
+            door.toggle(&mut hinge, &mut commands);
+        }
+    }
+}
+
 fn print_player_position() { //query: Query<(&Transform, &PlayerController)>) {
                              // for item in query.iter() {
                              //     println!("{:?}", item.0.translation);
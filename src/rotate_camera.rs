@@ -1,3 +1,5 @@
+use crate::generate_level::Climbable;
+use crate::physics::PLAYER_GRAVITY_SCALE;
 use bevy::ecs::event::{Events, ManualEventReader};
 use bevy::input::mouse::MouseMotion;
 use bevy::prelude::*;
@@ -83,6 +85,19 @@ fn is_grounted(
     }
 }
 
+/// Is the player inside the climbable volume of a ladder?
+fn is_climbing(
+    context: &Res<RapierContext>,
+    climbables: &Query<(), With<Climbable>>,
+    player_handle: Entity,
+) -> bool {
+    context
+        .intersections_with(player_handle)
+        .any(|(first, second, intersecting)| {
+            intersecting && (climbables.contains(first) || climbables.contains(second))
+        })
+}
+
 // / Handles keyboard input and movement
 fn player_move(
     keys: Res<Input<KeyCode>>,
@@ -95,15 +110,23 @@ fn player_move(
         Entity,
         &mut Velocity,
         &mut Damping,
+        &mut GravityScale,
     )>,
     q_child: Query<&Transform, With<FlyCam>>,
+    climbables: Query<(), With<Climbable>>,
     rapier_context: Res<RapierContext>,
 ) {
     if let Some(window) = windows.get_primary() {
         for mut transform in query.iter_mut() {
             let grounded = is_grounted(&rapier_context, transform.0.translation, transform.3);
+            let climbing = is_climbing(&rapier_context, &climbables, transform.3);
 
-            transform.5.linear_damping = if grounded.grounded { 8.0 } else { 1.0 };
+            transform.5.linear_damping = if grounded.grounded || climbing {
+                8.0
+            } else {
+                1.0
+            };
+            transform.6 .0 = if climbing { 0.0 } else { PLAYER_GRAVITY_SCALE };
 
             for &child in transform.2 {
                 match q_child.get(child) {
@@ -115,6 +138,8 @@ fn player_move(
                         for key in keys.get_pressed() {
                             if window.cursor_locked() {
                                 match key {
+                                    KeyCode::W if climbing => velocity += forward + Vec3::Y,
+                                    KeyCode::S if climbing => velocity -= forward + Vec3::Y,
                                     KeyCode::W => velocity += forward,
                                     KeyCode::S => velocity -= forward,
                                     KeyCode::A => velocity -= right,
@@ -128,11 +153,20 @@ fn player_move(
                                 }
                             }
                         }
-                        velocity = Quat::from_rotation_arc(Vec3::Y, grounded.normal)
-                            * velocity.normalize_or_zero()
-                            + Vec3::Y * 0.01;
-                        transform.1.impulse =
-                            velocity * settings.speed * if grounded.grounded { 1.0 } else { 0.128 };
+                        velocity = if climbing {
+                            velocity.normalize_or_zero()
+                        } else {
+                            Quat::from_rotation_arc(Vec3::Y, grounded.normal)
+                                * velocity.normalize_or_zero()
+                                + Vec3::Y * 0.01
+                        };
+                        transform.1.impulse = velocity
+                            * settings.speed
+                            * if grounded.grounded || climbing {
+                                1.0
+                            } else {
+                                0.128
+                            };
                     }
                     Err(_) => (),
                 }
@@ -12,3 +12,15 @@ pub fn is_all_same<T: PartialEq, U: Iterator<Item = T>>(mut el: U) -> bool {
         }
     }
 }
+
+/// Get the value following a `--name value` pair in the command line arguments
+pub fn get_arg(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != name);
+    args.next()?;
+    args.next()
+}
+
+/// Is the flag `name` passed on the command line?
+pub fn has_arg(name: &str) -> bool {
+    std::env::args().any(|arg| arg == name)
+}